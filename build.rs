@@ -4,7 +4,7 @@ use std::{borrow::Cow, process::Command};
 /// Generate the `cargo:` key output
 pub fn generate_cargo_keys() {
     let output = Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output();
 
     let commit = match output {
@@ -25,7 +25,9 @@ pub fn generate_cargo_keys() {
     println!(
         "cargo:rustc-env=RUST_WEB_DEV_VERSION={}",
         get_version(&commit)
-    )
+    );
+    println!("cargo:rustc-env=RUST_WEB_DEV_COMMIT={}", commit);
+    println!("cargo:rustc-env=RUST_WEB_DEV_PLATFORM={}", get_platform());
 }
 
 fn get_platform() -> String {
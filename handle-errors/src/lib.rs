@@ -1,17 +1,37 @@
-use reqwest::Error as ReqwestError;
 use argon2::Error as ArgonError;
+use reqwest::Error as ReqwestError;
 use reqwest_middleware::Error as MiddlwareReqwestError;
+use serde::Serialize;
 use std::fmt::Display;
 use tracing::{event, Level};
-use warp::{body::BodyDeserializeError, hyper::StatusCode, reject::Reject, Rejection, Reply};
+use warp::{
+    body::BodyDeserializeError,
+    cors::CorsForbidden,
+    hyper::StatusCode,
+    reject::{
+        InvalidHeader, InvalidQuery, LengthRequired, MethodNotAllowed, MissingHeader,
+        PayloadTooLarge, Reject, UnsupportedMediaType,
+    },
+    reply::Response,
+    Rejection, Reply,
+};
 
 #[derive(Debug)]
 pub enum Error {
     ParseError(std::num::ParseIntError),
     MissingParameters,
+    UnknownParameter(String),
+    InvalidParameter(String, String),
+    /// None of the media types in the `Accept` header can be produced
+    NotAcceptable(Vec<&'static str>),
+    NotFound,
     WrongPassword,
+    AccountAlreadyExists,
     CannotDecryptToken,
+    TokenRevoked,
     Unauthorized,
+    InappropriateContent(Vec<String>),
+    AlreadyFlagged,
     ArgonLibraryError(ArgonError),
     DatabaseQueryError(sqlx::Error),
     MigrationError(sqlx::migrate::MigrateError),
@@ -19,6 +39,7 @@ pub enum Error {
     MiddlwareReqwestApiError(MiddlwareReqwestError),
     ClientError(ApiLayerError),
     ServerError(ApiLayerError),
+    ProfanityCheckUnavailable,
 }
 
 #[derive(Debug, Clone)]
@@ -40,9 +61,27 @@ impl Display for Error {
         match self {
             Error::ParseError(err) => write!(f, "Cannot parse parameter: {}", err),
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::UnknownParameter(name) => write!(f, "Unknown parameter: {}", name),
+            Error::InvalidParameter(name, reason) => {
+                write!(f, "Invalid value for parameter {}: {}", name, reason)
+            }
+            Error::NotAcceptable(supported) => write!(
+                f,
+                "Cannot produce any of the accepted media types, available are: {}",
+                supported.join(", ")
+            ),
+            Error::NotFound => write!(f, "Resource not found"),
             Error::WrongPassword => write!(f, "Wrong password"),
-            Error::CannotDecryptToken => write!(f, "Cannot decrypt error"),
+            Error::AccountAlreadyExists => write!(f, "Account already exists"),
+            Error::CannotDecryptToken => write!(f, "Cannot decrypt token"),
+            Error::TokenRevoked => write!(f, "Token has been revoked"),
             Error::Unauthorized => write!(f, "No permission to change the underlying resource"),
+            Error::InappropriateContent(words) => write!(
+                f,
+                "Content contains inappropriate language: {}",
+                words.join(", ")
+            ),
+            Error::AlreadyFlagged => write!(f, "Content has already been flagged by this account"),
             Error::ArgonLibraryError(_) => write!(f, "Cannot verify password"),
             Error::DatabaseQueryError(_) => write!(f, "Query could not be executed"),
             Error::MigrationError(_) => write!(f, "Cannot migrate data"),
@@ -50,85 +89,292 @@ impl Display for Error {
             Error::MiddlwareReqwestApiError(err) => write!(f, "External API error: {}", err),
             Error::ClientError(err) => write!(f, "External Client error: {}", err),
             Error::ServerError(err) => write!(f, "External Server error: {}", err),
+            Error::ProfanityCheckUnavailable => {
+                write!(
+                    f,
+                    "Content can't be checked right now, please try again later"
+                )
+            }
         }
     }
 }
 
-pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
-    if let Some(Error::DatabaseQueryError(e)) = r.find() {
-        event!(Level::ERROR, "Database query error");
-
-        match e {
-            sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<i32>().unwrap() == 23505 {
-                    Ok(warp::reply::with_status(
-                        "Account already exists".to_string(), 
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                    ))
-                } else {
-                    Ok(warp::reply::with_status(
-                        "Cannot update data".to_string(),
-                        StatusCode::UNPROCESSABLE_ENTITY,
-                    ))
+impl Error {
+    /// HTTP status, stable error code and title clients can rely on.
+    /// Codes are part of the API and must not change once released.
+    fn kind(&self) -> (StatusCode, &'static str, &'static str) {
+        match self {
+            Error::ParseError(_) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                "Invalid parameter",
+            ),
+            Error::MissingParameters => (
+                StatusCode::BAD_REQUEST,
+                "missing_parameter",
+                "Missing parameter",
+            ),
+            Error::UnknownParameter(_) => (
+                StatusCode::BAD_REQUEST,
+                "unknown_parameter",
+                "Unknown parameter",
+            ),
+            Error::InvalidParameter(_, _) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_parameter",
+                "Invalid parameter",
+            ),
+            Error::NotAcceptable(_) => (
+                StatusCode::NOT_ACCEPTABLE,
+                "not_acceptable",
+                "Not acceptable",
+            ),
+            Error::NotFound => (StatusCode::NOT_FOUND, "not_found", "Resource not found"),
+            Error::WrongPassword => (
+                StatusCode::UNAUTHORIZED,
+                "wrong_credentials",
+                "Wrong E-Mail/Password combination",
+            ),
+            Error::AccountAlreadyExists => (
+                StatusCode::CONFLICT,
+                "account_exists",
+                "Account already exists",
+            ),
+            Error::CannotDecryptToken => (
+                StatusCode::UNAUTHORIZED,
+                "invalid_token",
+                "Invalid or expired token",
+            ),
+            Error::TokenRevoked => (
+                StatusCode::UNAUTHORIZED,
+                "token_revoked",
+                "Token has been revoked",
+            ),
+            Error::Unauthorized => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                "No permission to change the underlying resource",
+            ),
+            Error::InappropriateContent(_) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "inappropriate_content",
+                "Content contains inappropriate language",
+            ),
+            Error::AlreadyFlagged => (
+                StatusCode::CONFLICT,
+                "already_flagged",
+                "Content has already been flagged",
+            ),
+            Error::ArgonLibraryError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal Server Error",
+            ),
+            Error::DatabaseQueryError(sqlx::Error::RowNotFound) => {
+                (StatusCode::NOT_FOUND, "not_found", "Resource not found")
+            }
+            Error::DatabaseQueryError(sqlx::Error::Database(err)) => match err.code().as_deref() {
+                // unique_violation
+                Some("23505") => (
+                    StatusCode::CONFLICT,
+                    "already_exists",
+                    "Resource already exists",
+                ),
+                // Any other integrity constraint violation, like a foreign key
+                Some(code) if code.starts_with("23") => {
+                    (StatusCode::CONFLICT, "conflict", "Cannot update data")
                 }
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "database_error",
+                    "Query could not be executed",
+                ),
             },
-            _ => {
-                Ok(warp::reply::with_status(
-                    "Cannot update data".to_string(), 
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                ))
+            Error::DatabaseQueryError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+                "Query could not be executed",
+            ),
+            Error::MigrationError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal Server Error",
+            ),
+            Error::ReqwestApiError(_)
+            | Error::MiddlwareReqwestApiError(_)
+            | Error::ClientError(_)
+            | Error::ServerError(_) => (
+                StatusCode::BAD_GATEWAY,
+                "upstream_error",
+                "Content check failed",
+            ),
+            Error::ProfanityCheckUnavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "moderation_unavailable",
+                "Content check unavailable",
+            ),
+        }
+    }
+}
+
+/// Error response body following RFC 7807 (`application/problem+json`)
+#[derive(Serialize, Debug, Clone)]
+pub struct Problem {
+    /// URI reference identifying the problem type, derived from `code`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    /// Stable, machine-readable error code
+    pub code: &'static str,
+    pub request_id: String,
+    /// The offending words of `inappropriate_content` errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bad_words: Option<Vec<String>>,
+}
+
+impl Problem {
+    fn new(status: StatusCode, code: &'static str, title: &'static str, detail: String) -> Self {
+        Problem {
+            problem_type: format!("/problems/{}", code),
+            title,
+            status: status.as_u16(),
+            detail,
+            code,
+            request_id: String::new(),
+            bad_words: None,
+        }
+    }
+
+    fn from_error(error: &Error) -> Self {
+        let (status, code, title) = error.kind();
+        // Server side and database errors may contain internals, so their
+        // details only go to the log
+        let detail = if status.is_server_error() || matches!(error, Error::DatabaseQueryError(_)) {
+            title.to_string()
+        } else {
+            error.to_string()
+        };
+
+        let mut problem = Problem::new(status, code, title, detail);
+        if let Error::InappropriateContent(words) = error {
+            problem.bad_words = Some(words.clone());
+        }
+        problem
+    }
+
+    fn from_rejection(r: &Rejection) -> Self {
+        if let Some(error) = r.find::<Error>() {
+            return Problem::from_error(error);
+        }
+
+        // A request is matched against every route, so the rejection may
+        // combine several causes. Like warp, prefer anything over "method not
+        // allowed" and that over "not found".
+        if let Some(error) = r.find::<BodyDeserializeError>() {
+            Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_body",
+                "Invalid request body",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<MissingHeader>() {
+            if error.name().eq_ignore_ascii_case("authorization") {
+                Problem::new(
+                    StatusCode::UNAUTHORIZED,
+                    "missing_token",
+                    "Authentication required",
+                    error.to_string(),
+                )
+            } else {
+                Problem::new(
+                    StatusCode::BAD_REQUEST,
+                    "missing_header",
+                    "Missing request header",
+                    error.to_string(),
+                )
             }
+        } else if let Some(error) = r.find::<InvalidHeader>() {
+            Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_header",
+                "Invalid request header",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<InvalidQuery>() {
+            Problem::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_query",
+                "Invalid query string",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<LengthRequired>() {
+            Problem::new(
+                StatusCode::LENGTH_REQUIRED,
+                "length_required",
+                "Content-Length required",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<PayloadTooLarge>() {
+            Problem::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "Request body too large",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<UnsupportedMediaType>() {
+            Problem::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "Unsupported media type",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<CorsForbidden>() {
+            Problem::new(
+                StatusCode::FORBIDDEN,
+                "cors_forbidden",
+                "Cross-origin request forbidden",
+                error.to_string(),
+            )
+        } else if let Some(error) = r.find::<MethodNotAllowed>() {
+            Problem::new(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "method_not_allowed",
+                "Method not allowed",
+                error.to_string(),
+            )
+        } else {
+            Problem::new(
+                StatusCode::NOT_FOUND,
+                "route_not_found",
+                "Route not found",
+                "Route not found".to_string(),
+            )
+        }
+    }
+}
+
+/// Renders a rejection as `application/problem+json`, tagged with the ID
+/// of the request it belongs to.
+pub fn return_error(r: &Rejection, request_id: &str) -> Response {
+    let mut problem = Problem::from_rejection(r);
+    problem.request_id = request_id.to_string();
+    let status = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    if status.is_server_error() {
+        match r.find::<Error>() {
+            Some(error) => event!(Level::ERROR, code = problem.code, "{}: {:?}", error, error),
+            None => event!(Level::ERROR, code = problem.code, "{:?}", r),
         }
-    } else if let Some(Error::ReqwestApiError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::Unauthorized) = r.find() {
-         event!(Level::ERROR, "Not matching account id");
-        Ok(warp::reply::with_status(
-        "No permission to change underlying resource".to_string(),
-        StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(crate::Error::WrongPassword) = r.find() {
-        event!(Level::ERROR, "Entered wrong password");
-        Ok(warp::reply::with_status(
-            "Wrong E-Mail/Password combination".to_string(), 
-            StatusCode::UNAUTHORIZED
-        ))
-    } else if let Some(Error::MiddlwareReqwestApiError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::ClientError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::ServerError(e)) = r.find() {
-        event!(Level::ERROR, "{}", e);
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        )) 
-    } else if let Some(error) = r.find::<Error>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::RANGE_NOT_SATISFIABLE,
-        ))
-    } else if let Some(error) = r.find::<BodyDeserializeError>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::UNPROCESSABLE_ENTITY,
-        ))
     } else {
-        Ok(warp::reply::with_status(
-            "Route not found".to_owned(),
-            StatusCode::NOT_FOUND,
-        ))
+        event!(Level::INFO, code = problem.code, "{}", problem.detail);
     }
+
+    warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&problem), status),
+        "content-type",
+        "application/problem+json",
+    )
+    .into_response()
 }
@@ -0,0 +1,14 @@
+-- Add down migration script here
+-- Content added without an author gets a value from the sequence, as the
+-- serial default did before, so the columns can be required again
+UPDATE questions SET account_id = nextval('questions_account_id_seq') WHERE account_id IS NULL;
+
+ALTER TABLE questions
+ALTER COLUMN account_id SET DEFAULT nextval('questions_account_id_seq'),
+ALTER COLUMN account_id SET NOT NULL;
+
+UPDATE answers SET account_id = nextval('answers_account_id_seq') WHERE account_id IS NULL;
+
+ALTER TABLE answers
+ALTER COLUMN account_id SET DEFAULT nextval('answers_account_id_seq'),
+ALTER COLUMN account_id SET NOT NULL;
\ No newline at end of file
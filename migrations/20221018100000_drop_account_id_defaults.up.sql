@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE questions
+ALTER COLUMN account_id DROP DEFAULT,
+ALTER COLUMN account_id DROP NOT NULL;
+
+ALTER TABLE answers
+ALTER COLUMN account_id DROP DEFAULT,
+ALTER COLUMN account_id DROP NOT NULL;
\ No newline at end of file
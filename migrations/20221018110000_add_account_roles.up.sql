@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE accounts
+ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS revoked_sessions (
+	sid VARCHAR(36) PRIMARY KEY,
+	expires_on TIMESTAMPTZ NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,15 @@
+-- Add up migration script here
+ALTER TABLE questions
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	setweight(to_tsvector('english', title), 'A') ||
+	setweight(to_tsvector('english', content), 'B')
+) STORED;
+
+CREATE INDEX questions_search_vector_idx ON questions USING GIN (search_vector);
+
+ALTER TABLE answers
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+	to_tsvector('english', content)
+) STORED;
+
+CREATE INDEX answers_search_vector_idx ON answers USING GIN (search_vector);
\ No newline at end of file
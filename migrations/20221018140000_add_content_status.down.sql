@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE answers
+DROP COLUMN status;
+
+ALTER TABLE questions
+DROP COLUMN status;
\ No newline at end of file
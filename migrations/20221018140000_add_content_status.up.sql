@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE questions
+ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'published';
+
+ALTER TABLE answers
+ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'published';
\ No newline at end of file
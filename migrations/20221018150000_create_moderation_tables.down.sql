@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS flags;
+DROP TABLE IF EXISTS moderation_decisions;
\ No newline at end of file
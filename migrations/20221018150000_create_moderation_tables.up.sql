@@ -0,0 +1,24 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS moderation_decisions (
+	id serial PRIMARY KEY,
+	content_type VARCHAR(20) NOT NULL,
+	content_id INTEGER NOT NULL,
+	moderator_id INTEGER NOT NULL,
+	action VARCHAR(20) NOT NULL,
+	note TEXT,
+	created_on TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS flags (
+	id serial PRIMARY KEY,
+	content_type VARCHAR(20) NOT NULL,
+	content_id INTEGER NOT NULL,
+	account_id INTEGER,
+	reason TEXT NOT NULL,
+	created_on TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	decision_id INTEGER REFERENCES moderation_decisions(id)
+);
+
+-- Every account can only have one open flag per question or answer
+CREATE UNIQUE INDEX flags_open_idx ON flags (content_type, content_id, account_id)
+WHERE decision_id IS NULL;
\ No newline at end of file
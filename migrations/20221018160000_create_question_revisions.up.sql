@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS question_revisions (
+	id serial PRIMARY KEY,
+	question_id INTEGER NOT NULL REFERENCES questions(id) ON DELETE CASCADE,
+	account_id INTEGER,
+	title VARCHAR (255) NOT NULL,
+	content TEXT NOT NULL,
+	tags TEXT [],
+	created_on TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX question_revisions_question_idx ON question_revisions (question_id, id);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Deleting a question takes its answers with it
+ALTER TABLE answers
+DROP CONSTRAINT answers_corresponding_question_fkey,
+ADD CONSTRAINT answers_corresponding_question_fkey
+	FOREIGN KEY (corresponding_question) REFERENCES questions (id) ON DELETE CASCADE;
\ No newline at end of file
@@ -0,0 +1,80 @@
+use sqlx::migrate::Migrate;
+
+use crate::{
+    cli::MigrateCommand,
+    store::{postgres::MIGRATOR, Store},
+};
+
+pub async fn run(command: MigrateCommand, store: &Store) -> Result<(), String> {
+    match command {
+        MigrateCommand::Up => {
+            let before = applied_versions(store).await?;
+            MIGRATOR
+                .run(&store.pool)
+                .await
+                .map_err(|e| format!("Migration failed: {}", e))?;
+
+            let applied = up_migrations()
+                .filter(|(version, _)| !before.contains(version))
+                .count();
+            println!("Applied {} migration(s)", applied);
+            Ok(())
+        }
+        MigrateCommand::Down { steps } => {
+            let mut applied = applied_versions(store).await?;
+            applied.sort_unstable_by(|a, b| b.cmp(a));
+            if applied.is_empty() {
+                println!("No migrations to revert");
+                return Ok(());
+            }
+
+            // Everything newer than the first migration which is kept
+            let target = applied.get(steps).copied().unwrap_or(0);
+            MIGRATOR
+                .undo(&store.pool, target)
+                .await
+                .map_err(|e| format!("Reverting migrations failed: {}", e))?;
+
+            for version in applied.iter().take_while(|version| **version > target) {
+                println!("Reverted {}", version);
+            }
+            Ok(())
+        }
+        MigrateCommand::Status => {
+            let applied = applied_versions(store).await?;
+            for (version, description) in up_migrations() {
+                let state = if applied.contains(&version) {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{:<8} {} {}", state, version, description);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Versions and descriptions of the migrations embedded in the binary
+fn up_migrations() -> impl Iterator<Item = (i64, &'static str)> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| (migration.version, migration.description.as_ref()))
+}
+
+async fn applied_versions(store: &Store) -> Result<Vec<i64>, String> {
+    let mut conn = store
+        .pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Cannot connect to the database: {}", e))?;
+    conn.ensure_migrations_table()
+        .await
+        .map_err(|e| format!("Cannot create the migrations table: {}", e))?;
+
+    conn.list_applied_migrations()
+        .await
+        .map(|applied| applied.iter().map(|migration| migration.version).collect())
+        .map_err(|e| format!("Cannot list applied migrations: {}", e))
+}
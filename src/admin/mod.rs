@@ -0,0 +1,55 @@
+//! Administrative subcommands, run against the configured database instead
+//! of the HTTP API. Errors are returned as messages for the terminal.
+use handle_errors::Error;
+
+use crate::{
+    cli::{Command, ConfigCommand},
+    settings::{Args, StorageBackend},
+    store::Store,
+};
+
+mod migrate;
+mod questions;
+mod users;
+
+/// Runs every subcommand apart from `serve`
+pub async fn run(command: Command, config: Args) -> Result<(), String> {
+    match command {
+        Command::Serve { .. } => unreachable!("serve is handled by main"),
+        Command::Config(ConfigCommand::Check) => {
+            println!(
+                "Configuration is valid: {:?} storage, {:?} profanity checks, listening on {}:{}",
+                config.storage_backend,
+                config.profanity_backend,
+                config.bind_address,
+                config.app_port
+            );
+            Ok(())
+        }
+        Command::Migrate(command) => migrate::run(command, &connect(&config).await?).await,
+        Command::User(command) => users::run(command, &connect(&config).await?).await,
+        Command::Questions(command) => questions::run(command, &connect(&config).await?).await,
+    }
+}
+
+/// The `Display` of [`Error`] is written for API clients and leaves out
+/// what the database said, which is what an operator needs here
+fn describe(e: Error) -> String {
+    match e {
+        Error::DatabaseQueryError(e) => e.to_string(),
+        Error::MigrationError(e) => e.to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// The in-memory store lives inside the server process, so there is
+/// nothing to administrate from the outside
+async fn connect(config: &Args) -> Result<Store, String> {
+    if config.storage_backend != StorageBackend::Postgres {
+        return Err("Admin commands need storage_backend = \"postgres\"".to_string());
+    }
+
+    Store::new(&config.database_url())
+        .await
+        .map_err(|e| format!("Cannot connect to the database: {}", e))
+}
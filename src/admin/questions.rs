@@ -0,0 +1,391 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::describe;
+use crate::{
+    cli::QuestionsCommand,
+    store::Store,
+    types::{
+        moderation::ContentStatus,
+        transfer::{ImportReport, QuestionTransfer, TransferFormat},
+    },
+};
+
+/// Length of the `title` column
+const MAX_TITLE_LENGTH: usize = 255;
+
+/// How many questions are imported between two progress lines
+const PROGRESS_INTERVAL: usize = 100;
+
+/// A question in the CSV format, which has no nested values
+#[derive(Serialize, Deserialize, Debug)]
+struct CsvRow {
+    id: Option<i32>,
+    title: String,
+    content: String,
+    /// Separated by commas
+    #[serde(default)]
+    tags: Option<String>,
+    #[serde(default)]
+    status: Option<ContentStatus>,
+    /// JSON array of the answers, as in the other formats
+    #[serde(default)]
+    answers: Option<String>,
+}
+
+impl CsvRow {
+    fn into_question(self) -> Result<QuestionTransfer, String> {
+        let tags = self
+            .tags
+            .filter(|tags| !tags.trim().is_empty())
+            .map(|tags| {
+                tags.split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            });
+        let answers = match self.answers.as_deref().map(str::trim) {
+            None | Some("") => vec![],
+            Some(answers) => serde_json::from_str(answers)
+                .map_err(|e| format!("answers are not a JSON array of answers: {}", e))?,
+        };
+
+        Ok(QuestionTransfer {
+            id: self.id,
+            title: self.title,
+            content: self.content,
+            tags,
+            status: self.status.unwrap_or_default(),
+            answers,
+        })
+    }
+
+    fn from_question(question: &QuestionTransfer) -> Result<Self, serde_json::Error> {
+        let answers = match question.answers.is_empty() {
+            true => None,
+            false => Some(serde_json::to_string(&question.answers)?),
+        };
+
+        Ok(CsvRow {
+            id: question.id,
+            title: question.title.clone(),
+            content: question.content.clone(),
+            tags: question.tags.as_ref().map(|tags| tags.join(",")),
+            status: Some(question.status),
+            answers,
+        })
+    }
+}
+
+pub async fn run(command: QuestionsCommand, store: &Store) -> Result<(), String> {
+    match command {
+        QuestionsCommand::Import {
+            path,
+            format,
+            conflict,
+            dry_run,
+            owner,
+        } => {
+            let format = format
+                .or_else(|| TransferFormat::from_path(&path))
+                .ok_or_else(|| format!("Cannot tell the format of {}, use --format", path))?;
+            let questions = read_questions(&path, format)?;
+            let owner = match owner {
+                Some(owner) => Some(super::users::account_id(store, &owner).await?),
+                None => None,
+            };
+
+            let total = questions.len();
+            eprintln!(
+                "Importing {} question(s) from {} as {}, {} on conflicts{}",
+                total,
+                path,
+                format,
+                conflict,
+                if dry_run { " (dry run)" } else { "" }
+            );
+            let report = store
+                .import_questions(questions, owner, conflict, dry_run, |report| {
+                    let processed = report.processed();
+                    if processed % PROGRESS_INTERVAL == 0 && processed < total {
+                        eprintln!("{}/{} questions", processed, total);
+                    }
+                })
+                .await
+                .map_err(|e| format!("Import failed, nothing was written: {}", describe(e)))?;
+
+            print_report(&report, dry_run);
+            Ok(())
+        }
+        QuestionsCommand::Export { output, format } => {
+            let format = format
+                .or_else(|| output.as_deref().and_then(TransferFormat::from_path))
+                .unwrap_or(TransferFormat::Json);
+            let questions = store
+                .export_questions()
+                .await
+                .map_err(|e| format!("Cannot read questions: {}", describe(e)))?;
+
+            // Not `println!`, which panics once a pipe like `| head` closes
+            let writer: Box<dyn Write> = match &output {
+                Some(path) => Box::new(
+                    fs::File::create(path).map_err(|e| format!("Cannot write {}: {}", path, e))?,
+                ),
+                None => Box::new(io::stdout()),
+            };
+            write_questions(BufWriter::new(writer), &questions, format)
+                .map_err(|e| format!("Cannot write questions: {}", e))?;
+
+            if let Some(path) = output {
+                eprintln!(
+                    "Exported {} question(s) to {} as {}",
+                    questions.len(),
+                    path,
+                    format
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Parses and validates the whole file, reporting every problem found
+/// instead of stopping at the first one
+fn read_questions(path: &str, format: TransferFormat) -> Result<Vec<QuestionTransfer>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let mut errors = vec![];
+    // Together with where they came from, for the error messages
+    let mut questions: Vec<(String, QuestionTransfer)> = vec![];
+
+    match format {
+        TransferFormat::Json => {
+            let entries: BTreeMap<String, QuestionTransfer> =
+                serde_json::from_reader(BufReader::new(file))
+                    .map_err(|e| format!("{} is not in the questions.json format: {}", path, e))?;
+            for (key, mut question) in entries {
+                if question.id.is_none() {
+                    question.id = key.parse().ok();
+                }
+                questions.push((format!("entry {:?}", key), question));
+            }
+            // Keys are strings, so "10" would come before "2"
+            questions.sort_by_key(|(_, question)| question.id.unwrap_or(i32::MAX));
+        }
+        TransferFormat::Ndjson => {
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(|e| format!("Cannot read {}: {}", path, e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let source = format!("line {}", index + 1);
+                match serde_json::from_str(&line) {
+                    Ok(question) => questions.push((source, question)),
+                    Err(e) => errors.push(format!("{}: {}", source, e)),
+                }
+            }
+        }
+        TransferFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(file);
+            for (index, row) in reader.deserialize::<CsvRow>().enumerate() {
+                // Not the line, since quoted fields can span several
+                let source = format!("row {}", index + 1);
+                match row
+                    .map_err(|e| e.to_string())
+                    .and_then(CsvRow::into_question)
+                {
+                    Ok(question) => questions.push((source, question)),
+                    Err(e) => errors.push(format!("{}: {}", source, e)),
+                }
+            }
+        }
+    }
+
+    let mut ids = HashSet::new();
+    for (source, question) in &questions {
+        if let Some(id) = question.id {
+            if id < 1 {
+                errors.push(format!("{}: id {} is not positive", source, id));
+            } else if !ids.insert(id) {
+                errors.push(format!("{}: id {} appears more than once", source, id));
+            }
+        }
+        if question.title.trim().is_empty() {
+            errors.push(format!("{}: title is empty", source));
+        } else if question.title.chars().count() > MAX_TITLE_LENGTH {
+            errors.push(format!(
+                "{}: title is longer than {} characters",
+                source, MAX_TITLE_LENGTH
+            ));
+        }
+        if question.content.trim().is_empty() {
+            errors.push(format!("{}: content is empty", source));
+        }
+        if question
+            .answers
+            .iter()
+            .any(|answer| answer.content.trim().is_empty())
+        {
+            errors.push(format!("{}: an answer is empty", source));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(questions
+            .into_iter()
+            .map(|(_, question)| question)
+            .collect())
+    } else {
+        Err(format!(
+            "{} is invalid, nothing was imported:\n  - {}",
+            path,
+            errors.join("\n  - ")
+        ))
+    }
+}
+
+fn write_questions<W: Write>(
+    mut writer: W,
+    questions: &[QuestionTransfer],
+    format: TransferFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        TransferFormat::Json => {
+            let entries: BTreeMap<String, &QuestionTransfer> = questions
+                .iter()
+                .filter_map(|question| Some((question.id?.to_string(), question)))
+                .collect();
+            serde_json::to_writer_pretty(&mut writer, &entries)?;
+            writeln!(writer)?;
+        }
+        TransferFormat::Ndjson => {
+            for question in questions {
+                serde_json::to_writer(&mut writer, question)?;
+                writeln!(writer)?;
+            }
+        }
+        TransferFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(&mut writer);
+            for question in questions {
+                csv.serialize(CsvRow::from_question(question)?)?;
+            }
+            csv.flush()?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn print_report(report: &ImportReport, dry_run: bool) {
+    println!(
+        "{}{} created, {} overwritten, {} renumbered, {} skipped, {} answer(s) added",
+        if dry_run {
+            "Dry run, nothing was written. Would have: "
+        } else {
+            ""
+        },
+        report.created,
+        report.overwritten,
+        report.renumbered,
+        report.skipped,
+        report.answers
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::types::transfer::AnswerTransfer;
+
+    fn file(name: &str, content: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    fn questions() -> Vec<QuestionTransfer> {
+        vec![
+            QuestionTransfer {
+                id: Some(1),
+                title: "First, with \"quotes\"".to_string(),
+                content: "Spans\ntwo lines".to_string(),
+                tags: Some(vec!["rust".to_string(), "async".to_string()]),
+                status: ContentStatus::Hidden,
+                answers: vec![AnswerTransfer {
+                    content: "An answer".to_string(),
+                    status: ContentStatus::Pending,
+                }],
+            },
+            QuestionTransfer {
+                id: Some(2),
+                title: "Second".to_string(),
+                content: "Content".to_string(),
+                tags: None,
+                status: ContentStatus::Published,
+                answers: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn exports_can_be_imported_again() {
+        for (format, extension) in [
+            (TransferFormat::Json, "json"),
+            (TransferFormat::Ndjson, "ndjson"),
+            (TransferFormat::Csv, "csv"),
+        ] {
+            let mut exported = vec![];
+            write_questions(&mut exported, &questions(), format).unwrap();
+            let path = file(&format!("export.{}", extension), &exported);
+
+            assert_eq!(
+                read_questions(path.to_str().unwrap(), format).unwrap(),
+                questions(),
+                "{} export",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn csv_columns_after_content_are_optional() {
+        let path = file("minimal.csv", b"id,title,content\n,Title,Content\n");
+        let imported = read_questions(path.to_str().unwrap(), TransferFormat::Csv).unwrap();
+
+        assert_eq!(imported[0].id, None);
+        assert_eq!(imported[0].tags, None);
+        assert_eq!(imported[0].status, ContentStatus::Published);
+        assert!(imported[0].answers.is_empty());
+    }
+
+    #[test]
+    fn every_problem_of_a_file_is_reported() {
+        let path = file(
+            "invalid.ndjson",
+            br#"{"id": 1, "title": "Title", "content": "Content"}
+{"id": "1", "title": " ", "content": "Content", "answers": [{"content": ""}]}
+not json
+{"id": 0, "title": "Title", "content": "", "status": "archived"}
+"#,
+        );
+
+        let error = read_questions(path.to_str().unwrap(), TransferFormat::Ndjson).unwrap_err();
+        for problem in [
+            "line 3: expected ident",
+            "line 4: unknown variant `archived`",
+            "line 2: id 1 appears more than once",
+            "line 2: title is empty",
+            "line 2: an answer is empty",
+        ] {
+            assert!(error.contains(problem), "{:?} misses {:?}", error, problem);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::env;
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use super::describe;
+use crate::{
+    cli::UserCommand,
+    routes::authentication::hash,
+    store::{AccountRepository, Store},
+    types::account::{Account, AccountId},
+};
+
+pub async fn run(command: UserCommand, store: &Store) -> Result<(), String> {
+    match command {
+        UserCommand::Create {
+            email,
+            role,
+            password_env,
+        } => {
+            let password = password(password_env.as_deref())?;
+            store
+                .add_account(Account {
+                    id: None,
+                    email: email.clone(),
+                    password: hash(password.as_bytes()),
+                    role,
+                })
+                .await
+                .map_err(|e| format!("Cannot create account {}: {}", email, describe(e)))?;
+
+            println!("Created {} account {}", role, email);
+            if password_env.is_none() {
+                println!("Password: {}", password);
+            }
+            Ok(())
+        }
+        UserCommand::SetRole { email, role } => {
+            let id = account_id(store, &email).await?;
+            store
+                .set_account_role(id.0, role)
+                .await
+                .map_err(|e| format!("Cannot change role of {}: {}", email, describe(e)))?;
+
+            println!("{} is now {}", email, role);
+            Ok(())
+        }
+        UserCommand::ResetPassword {
+            email,
+            password_env,
+        } => {
+            let id = account_id(store, &email).await?;
+            let password = password(password_env.as_deref())?;
+            store
+                .set_account_password(id.0, hash(password.as_bytes()))
+                .await
+                .map_err(|e| format!("Cannot reset password of {}: {}", email, describe(e)))?;
+
+            println!("Password of {} reset", email);
+            if password_env.is_none() {
+                println!("Password: {}", password);
+            }
+            Ok(())
+        }
+        UserCommand::Delete { email } => {
+            let id = account_id(store, &email).await?;
+            store
+                .delete_account(id.0)
+                .await
+                .map_err(|e| format!("Cannot delete {}: {}", email, describe(e)))?;
+
+            println!("Deleted account {}", email);
+            Ok(())
+        }
+    }
+}
+
+/// Takes the password from `password_env` if given, so it doesn't end up in
+/// the shell history, otherwise generates one
+fn password(password_env: Option<&str>) -> Result<String, String> {
+    match password_env {
+        Some(name) => match env::var(name) {
+            Ok(password) if !password.is_empty() => Ok(password),
+            _ => Err(format!("{} is not set", name)),
+        },
+        None => Ok(rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(20)
+            .map(char::from)
+            .collect()),
+    }
+}
+
+pub(super) async fn account_id(store: &Store, email: &str) -> Result<AccountId, String> {
+    match store.get_account(email.to_string()).await {
+        Ok(account) => account
+            .id
+            .ok_or_else(|| format!("Account {} has no id", email)),
+        Err(handle_errors::Error::NotFound) => {
+            Err(format!("No account with the e-mail address {}", email))
+        }
+        Err(e) => Err(format!("Cannot look up {}: {}", email, describe(e))),
+    }
+}
@@ -0,0 +1,176 @@
+//! Stand-in for the apilayer.com bad words API, so the moderation path can
+//! be run without internet access and with deterministic results.
+//!
+//! ```sh
+//! cargo run --features fake-bad-words --bin fake-bad-words
+//! ```
+//!
+//! Point `[profanity_api]` in `setup.toml` at it with
+//! `url = "http://localhost:8089/bad_words"` and set the API key to the one
+//! in `FAKE_BAD_WORDS_API_KEY` (`test-key` by default). Words from
+//! `wordlist.txt` are censored. Content containing one of the following
+//! markers triggers the error paths instead:
+//!
+//! - `__client_error__` answers `400 Bad Request`
+//! - `__server_error__` answers `500 Internal Server Error`
+//! - `__unavailable__` answers `503 Service Unavailable`, which is retried
+//! - `__slow__` answers after 30 seconds, to run into the client timeout
+//!
+//! The tests of the bad words client include this file as a module and
+//! serve [`routes`] on a free port.
+#![warn(clippy::all)]
+use std::{collections::HashMap, env, time::Duration};
+
+use serde::Serialize;
+use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+#[derive(Serialize, Debug, Clone)]
+struct BadWord {
+    original: String,
+    word: String,
+    deviations: i64,
+    info: i64,
+    #[serde(rename = "replacedLen")]
+    replaced_len: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BadWordsResponse {
+    content: String,
+    bad_words_total: i64,
+    bad_words_list: Vec<BadWord>,
+    censored_content: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ApiResponse {
+    message: String,
+}
+
+fn error(message: &str, status: StatusCode) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(
+        warp::reply::json(&ApiResponse {
+            message: message.to_string(),
+        }),
+        status,
+    )
+}
+
+fn censor(content: &str, censor_character: &str, words: &[String]) -> BadWordsResponse {
+    let mut bad_words_list = vec![];
+    let censored_content = content
+        .split(' ')
+        .map(|token| {
+            let word = token
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            if word.is_empty() || !words.contains(&word) {
+                return token.to_string();
+            }
+
+            bad_words_list.push(BadWord {
+                original: word.clone(),
+                word: word.clone(),
+                deviations: 0,
+                info: 2,
+                replaced_len: word.chars().count() as i64,
+            });
+            token
+                .to_lowercase()
+                .replace(&word, &censor_character.repeat(word.chars().count()))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    BadWordsResponse {
+        content: content.to_string(),
+        bad_words_total: bad_words_list.len() as i64,
+        bad_words_list,
+        censored_content,
+    }
+}
+
+async fn bad_words(
+    params: HashMap<String, String>,
+    api_key: Option<String>,
+    body: Bytes,
+    expected_key: String,
+    words: Vec<String>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if api_key.as_deref() != Some(expected_key.as_str()) {
+        return Ok(error(
+            "Invalid authentication credentials",
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let content = String::from_utf8_lossy(&body);
+    if content.contains("__client_error__") {
+        return Ok(error("Bad request", StatusCode::BAD_REQUEST));
+    }
+    if content.contains("__server_error__") {
+        return Ok(error(
+            "Something went wrong",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ));
+    }
+    if content.contains("__unavailable__") {
+        return Ok(error(
+            "Service unavailable",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    }
+    if content.contains("__slow__") {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    }
+
+    let censor_character = params.get("censor_character").map_or("*", String::as_str);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&censor(&content, censor_character, &words)),
+        StatusCode::OK,
+    ))
+}
+
+/// The words of `wordlist.txt`, which the fake censors
+pub fn wordlist() -> Vec<String> {
+    include_str!("../../wordlist.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// `POST /bad_words`, answering requests with `expected_key` as API key
+pub fn routes(
+    expected_key: String,
+    words: Vec<String>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(warp::path("bad_words"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("apiKey"))
+        .and(warp::body::bytes())
+        .and(warp::any().map(move || expected_key.clone()))
+        .and(warp::any().map(move || words.clone()))
+        .and_then(bad_words)
+}
+
+#[tokio::main]
+async fn main() {
+    let port = env::var("FAKE_BAD_WORDS_PORT")
+        .ok()
+        .and_then(|port| port.parse::<u16>().ok())
+        .unwrap_or(8089);
+    let expected_key =
+        env::var("FAKE_BAD_WORDS_API_KEY").unwrap_or_else(|_| "test-key".to_string());
+
+    println!(
+        "fake bad words API listening on http://localhost:{}/bad_words",
+        port
+    );
+    warp::serve(routes(expected_key, wordlist()))
+        .run(([127, 0, 0, 1], port))
+        .await;
+}
@@ -0,0 +1,117 @@
+use clap::{Parser, Subcommand};
+
+use crate::types::{
+    account::Role,
+    transfer::{ConflictMode, TransferFormat},
+};
+
+/// Q&A web service
+#[derive(Debug, Parser)]
+#[clap(version = env!("RUST_WEB_DEV_VERSION"))]
+pub struct Cli {
+    /// Configuration file, with or without the `.toml` extension
+    #[clap(long, global = true, default_value = "setup")]
+    pub config: String,
+    /// Serves HTTP if left out
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the HTTP API
+    Serve {
+        /// Don't apply pending migrations on startup
+        #[clap(long)]
+        skip_migrations: bool,
+    },
+    /// Manage the database schema
+    #[clap(subcommand)]
+    Migrate(MigrateCommand),
+    /// Manage accounts
+    #[clap(subcommand)]
+    User(UserCommand),
+    /// Move questions in and out of the database
+    #[clap(subcommand)]
+    Questions(QuestionsCommand),
+    /// Inspect the configuration
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the most recently applied migrations
+    Down {
+        /// Number of migrations to revert
+        #[clap(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List every migration and whether it was applied
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Create an account, printing a generated password unless one is given
+    Create {
+        email: String,
+        #[clap(long, default_value_t = Role::User)]
+        role: Role,
+        /// Read the password from this environment variable instead
+        #[clap(long)]
+        password_env: Option<String>,
+    },
+    /// Change the role of an account
+    SetRole { email: String, role: Role },
+    /// Replace the password of an account with a generated one
+    ResetPassword {
+        email: String,
+        /// Read the new password from this environment variable instead
+        #[clap(long)]
+        password_env: Option<String>,
+    },
+    /// Delete an account
+    Delete { email: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QuestionsCommand {
+    /// Add the questions and answers of a file in a single transaction
+    Import {
+        path: String,
+        /// json (keyed like `questions.json`), ndjson or csv, guessed from
+        /// the file extension if left out
+        #[clap(long)]
+        format: Option<TransferFormat>,
+        /// What to do with questions whose id is taken: skip, overwrite or
+        /// renumber
+        #[clap(long, default_value_t = ConflictMode::Skip)]
+        conflict: ConflictMode,
+        /// Validate the file and report what would change, without writing
+        #[clap(long)]
+        dry_run: bool,
+        /// E-mail address of the account the content is added for, left
+        /// without author otherwise
+        #[clap(long)]
+        owner: Option<String>,
+    },
+    /// Write all questions with their answers
+    Export {
+        /// Write to this file instead of stdout
+        #[clap(long)]
+        output: Option<String>,
+        /// json, ndjson or csv, guessed from the output file extension and
+        /// json otherwise
+        #[clap(long)]
+        format: Option<TransferFormat>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Load and validate the configuration without starting the server
+    Check,
+}
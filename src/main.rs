@@ -1,55 +1,72 @@
 #![warn(clippy::all)]
+mod admin;
+mod cli;
+mod metrics;
 mod profanity;
 mod routes;
+mod settings;
+mod shutdown;
 mod store;
+mod tls;
 mod types;
-use std::env;
+use std::{future::Future, net::SocketAddr, pin::Pin, process, sync::Arc, time::Duration};
 
-use config::Config;
+use clap::Parser;
+use cli::{Cli, Command, ConfigCommand};
 use dotenv::dotenv;
-use handle_errors::return_error;
-use routes::{
-    answer::add_answer,
-    question::{add_question, delete_question, get_questions, update_question},
+use profanity::{
+    ApiLayerChecker, CachedChecker, CircuitBreaker, ContentPolicy, DynProfanityChecker,
+    WordlistChecker,
 };
-use store::Store;
+use routes::request_id::{request_span, with_request_id, REQUEST_ID_HEADER};
+use settings::{Args, LogFormat, ProfanityBackend, StorageBackend};
+use shutdown::Draining;
+use sqlx::PgPool;
+use store::{DynStore, InMemoryStore, Store};
+use tokio::sync::watch;
 use tracing_subscriber::fmt::format::FmtSpan;
 use warp::{http::Method, Filter};
 
-#[derive(Debug, Default, serde::Deserialize, PartialEq)]
-struct Args {
-    log_level: String,
-    database_host: String,
-    database_port: u16,
-    database_name: String,
-    app_port: u16,
-    database_username: String,
-    database_password: String,
-}
-
 #[tokio::main]
 async fn main() -> Result<(), handle_errors::Error> {
-    let config = Config::builder()
-        .add_source(config::File::with_name("setup"))
-        .build()
-        .unwrap();
-    let config = config.try_deserialize::<Args>().unwrap();
-
+    let cli = Cli::parse();
+    // Before loading the configuration, so `.env` can override it too
     dotenv().ok();
 
-    if env::var("BAD_WORDS_API_KEY").is_err() {
-        panic!("BAD_WORDS_API_KEY not set");
-    }
-
-    if env::var("PASETO_KEY").is_err() {
-        panic!("PASETO_KEY not set");
+    let command = cli.command.unwrap_or(Command::Serve {
+        skip_migrations: false,
+    });
+    // Checking the configuration covers everything serving needs
+    let serving = matches!(
+        command,
+        Command::Serve { .. } | Command::Config(ConfigCommand::Check)
+    );
+
+    let config = match Args::load(&cli.config, serving) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    match command {
+        Command::Serve { skip_migrations } => serve(config, !skip_migrations).await,
+        command => {
+            if let Err(e) = admin::run(command, config).await {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            Ok(())
+        }
     }
+}
 
-    let port = std::env::var("PORT")
-        .ok()
-        .map(|val| val.parse::<u16>())
-        .unwrap_or(Ok(config.app_port))
-        .map_err(handle_errors::Error::ParseError)?;
+async fn serve(config: Args, migrate: bool) -> Result<(), handle_errors::Error> {
+    let port = config.app_port;
+    let addr = SocketAddr::new(config.bind_address, port);
+    let database_url = config.database_url();
+    routes::authentication::set_paseto_key(config.secrets.paseto_key.clone());
 
     let log_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
         format!(
@@ -58,102 +75,160 @@ async fn main() -> Result<(), handle_errors::Error> {
         )
     });
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         // Use the filter we built above to determine which traces to record.
         .with_env_filter(log_filter)
         // Record an event when each span closes. This can be used to time our
         // routes' durations!
-        .with_span_events(FmtSpan::CLOSE)
-        .init();
+        .with_span_events(FmtSpan::CLOSE);
+    match config.log_format {
+        LogFormat::Text => subscriber.init(),
+        // The fields of the current span, like the request ID, are added to every line
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+    }
 
     tracing::info!("Q&A service build ID {}", env!("RUST_WEB_DEV_VERSION"));
 
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_header("content-type")
+        .allow_headers(vec!["content-type", "authorization", REQUEST_ID_HEADER])
+        .expose_headers(vec![REQUEST_ID_HEADER])
         .allow_methods(&[Method::PUT, Method::DELETE, Method::GET, Method::POST]);
 
-    let store = Store::new(&format!(
-        "postgres://{username}:{password}@{host}:{port}/{name}",
-        username = config.database_username,
-        password = config.database_password,
-        host = config.database_host,
-        port = config.database_port,
-        name = config.database_name
-    ))
-    .await
-    .map_err(handle_errors::Error::DatabaseQueryError)?;
-
-    sqlx::migrate!()
-        .run(&store.clone().pool)
-        .await
-        .map_err(handle_errors::Error::MigrationError)?;
-
-    let store_filter = warp::any().map(move || store.clone());
-
-    let get_questions = warp::get()
-        .and(warp::path("questions"))
-        .and(warp::path::end())
-        .and(warp::query())
-        .and(store_filter.clone())
-        .and_then(get_questions);
-
-    let add_question = warp::post()
-        .and(warp::path("questions"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(add_question);
-
-    let update_question = warp::put()
-        .and(warp::path("questions"))
-        .and(warp::path::param::<i32>())
-        .and(warp::path::end())
-        .and(routes::authentication::auth())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(update_question);
-
-    let delete_question = warp::delete()
-        .and(warp::path("questions"))
-        .and(warp::path::param::<i32>())
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and_then(delete_question);
-
-    let add_answer = warp::post()
-        .and(warp::path("answers"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::form())
-        .and_then(add_answer);
-
-    let registration = warp::post()
-        .and(warp::path("registration"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(routes::authentication::register);
-
-    let login = warp::post()
-        .and(warp::path("login"))
-        .and(warp::path::end())
-        .and(store_filter.clone())
-        .and(warp::body::json())
-        .and_then(routes::authentication::login);
-
-    let routes = get_questions
-        .or(add_question)
-        .or(update_question)
-        .or(delete_question)
-        .or(add_answer)
-        .or(registration)
-        .or(login)
-        .with(cors)
-        .with(warp::trace::request())
-        .recover(return_error);
-
-    warp::serve(routes).run(([127, 0, 0, 1], port)).await;
+    let profanity: DynProfanityChecker = match config.profanity_backend {
+        ProfanityBackend::Api => {
+            let api_key = config.secrets.profanity_api_key.clone().unwrap_or_default();
+            let api = match ApiLayerChecker::new(&config.profanity_api, api_key) {
+                Ok(api) => Arc::new(api),
+                Err(e) => {
+                    tracing::error!("Cannot build profanity API client: {}", e);
+                    process::exit(1);
+                }
+            };
+
+            // Cached results are still served while the circuit is open
+            let breaker = Arc::new(CircuitBreaker::new(api, config.profanity_breaker));
+            Arc::new(CachedChecker::new(breaker, config.profanity_cache))
+        }
+        ProfanityBackend::Wordlist => {
+            match WordlistChecker::from_file(&config.profanity_wordlist) {
+                Ok(checker) => Arc::new(checker),
+                Err(e) => {
+                    tracing::error!(
+                        "Cannot read profanity wordlist {}: {}",
+                        config.profanity_wordlist,
+                        e
+                    );
+                    process::exit(1);
+                }
+            }
+        }
+    };
+
+    // The pool is kept to close it on shutdown
+    let (store, pool): (DynStore, Option<PgPool>) = match config.storage_backend {
+        StorageBackend::Postgres => {
+            let store = Store::new(&database_url)
+                .await
+                .map_err(handle_errors::Error::DatabaseQueryError)?;
+
+            if migrate {
+                store::postgres::MIGRATOR
+                    .run(&store.clone().pool)
+                    .await
+                    .map_err(handle_errors::Error::MigrationError)?;
+            }
+
+            if let Err(e) =
+                prometheus::register(Box::new(metrics::PoolCollector::new(store.pool.clone())))
+            {
+                tracing::warn!("Cannot register database pool metrics: {}", e);
+            }
+
+            let pool = store.pool.clone();
+            (Arc::new(store), Some(pool))
+        }
+        StorageBackend::Memory => {
+            tracing::warn!("Using in-memory storage, data will be lost on shutdown");
+            (Arc::new(InMemoryStore::seeded()), None)
+        }
+    };
+
+    let policy = ContentPolicy::new(
+        profanity,
+        config.question_moderation,
+        config.answer_moderation,
+    );
+
+    let draining = Draining::default();
+    let routes = with_request_id(routes::router(store, policy, draining.clone()).with(cors))
+        .with(warp::trace(request_span))
+        .with(warp::log::custom(metrics::record_request));
+
+    // Flips readiness first, and only stops accepting connections once load
+    // balancers had the time to notice
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let readiness_delay = Duration::from_secs(config.shutdown.readiness_delay_seconds);
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        tracing::info!(
+            "Shutdown requested, stopping to accept connections in {:?}",
+            readiness_delay
+        );
+        draining.start();
+        tokio::time::sleep(readiness_delay).await;
+        let _ = stop_tx.send(true);
+    });
+
+    let mut stop = stop_rx.clone();
+    let stopped = async move {
+        let _ = stop.changed().await;
+    };
+    let server: Pin<Box<dyn Future<Output = ()> + Send>> = if config.tls.enabled {
+        let (addr, server) = warp::serve(routes)
+            .tls()
+            .cert_path(&config.tls.cert_path)
+            .key_path(&config.tls.key_path)
+            .bind_with_graceful_shutdown(addr, stopped);
+        tracing::info!("Listening on https://{}", addr);
+        Box::pin(server)
+    } else {
+        let (addr, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, stopped);
+        tracing::info!("Listening on http://{}", addr);
+        Box::pin(server)
+    };
+    let server = tokio::spawn(server);
+
+    if let (true, Some(redirect_port)) = (config.tls.enabled, config.tls.redirect_port) {
+        let mut stop = stop_rx.clone();
+        let (redirect_addr, redirect) = warp::serve(tls::redirect(port))
+            .bind_with_graceful_shutdown(
+                SocketAddr::new(config.bind_address, redirect_port),
+                async move {
+                    let _ = stop.changed().await;
+                },
+            );
+        tracing::info!("Redirecting http://{} to HTTPS", redirect_addr);
+        tokio::spawn(redirect);
+    }
+
+    let mut stop = stop_rx;
+    let _ = stop.changed().await;
+    let drain_timeout = Duration::from_secs(config.shutdown.drain_timeout_seconds);
+    tracing::info!("Draining connections for up to {:?}", drain_timeout);
+    match tokio::time::timeout(drain_timeout, server).await {
+        Ok(_) => tracing::info!("All connections drained"),
+        Err(_) => tracing::warn!("Drain timeout elapsed, cutting off remaining connections"),
+    }
+
+    if let Some(pool) = pool {
+        pool.close().await;
+        tracing::info!("Database connections closed");
+    }
 
     Ok(())
 }
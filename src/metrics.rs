@@ -0,0 +1,168 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    core::{Collector, Desc},
+    proto::MetricFamily,
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, Opts, TextEncoder,
+};
+use sqlx::PgPool;
+
+lazy_static! {
+    pub static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "Number of HTTP requests by route, method and status code",
+        &["route", "method", "status"]
+    )
+    .unwrap();
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "HTTP request latencies by route and method",
+        &["route", "method"]
+    )
+    .unwrap();
+    pub static ref PROFANITY_API_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "profanity_api_requests_total",
+        "Number of calls to the bad words API by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref PROFANITY_API_DURATION: HistogramVec = register_histogram_vec!(
+        "profanity_api_request_duration_seconds",
+        "Bad words API latencies by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref PROFANITY_CIRCUIT_STATE: IntGaugeVec = register_int_gauge_vec!(
+        "profanity_api_circuit_state",
+        "State of the bad words API circuit breaker, 1 for the current one",
+        &["state"]
+    )
+    .unwrap();
+    pub static ref PROFANITY_CIRCUIT_TRANSITIONS: IntCounterVec = register_int_counter_vec!(
+        "profanity_api_circuit_transitions_total",
+        "Number of times the bad words API circuit breaker changed to a state",
+        &["state"]
+    )
+    .unwrap();
+    pub static ref LOGINS: IntCounterVec = register_int_counter_vec!(
+        "logins_total",
+        "Number of login attempts by outcome",
+        &["outcome"]
+    )
+    .unwrap();
+}
+
+/// The routes of [`crate::routes::router`], with `{id}` for numeric path
+/// segments. Other paths are counted as `unmatched`, so scanners can't
+/// create an unbounded number of series.
+const ROUTES: &[&str] = &[
+    "/questions",
+    "/questions/search",
+    "/questions/{id}",
+    "/questions/{id}/answers",
+    "/questions/{id}/flags",
+    "/questions/{id}/revisions",
+    "/questions/{id}/revisions/{id}/rollback",
+    "/answers",
+    "/answers/{id}",
+    "/answers/{id}/flags",
+    "/moderation/queue",
+    "/moderation/questions/{id}/decisions",
+    "/moderation/answers/{id}/decisions",
+    "/registration",
+    "/login",
+    "/token/refresh",
+    "/logout",
+    "/accounts",
+    "/accounts/{id}",
+    "/accounts/{id}/role",
+    "/health",
+    "/ready",
+    "/version",
+    "/metrics",
+];
+
+/// Turns a request path into the route it belongs to, e.g.
+/// `/questions/1/answers` into `/questions/{id}/answers`
+fn route_label(path: &str) -> &'static str {
+    let route = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.parse::<i32>() {
+            Ok(_) => "{id}",
+            Err(_) => segment,
+        })
+        .fold(String::new(), |route, segment| route + "/" + segment);
+
+    ROUTES
+        .iter()
+        .find(|known| **known == route)
+        .copied()
+        .unwrap_or("unmatched")
+}
+
+/// Records a finished request, meant for `warp::log::custom`
+pub fn record_request(info: warp::log::Info) {
+    let route = route_label(info.path());
+    let method = info.method().as_str();
+
+    HTTP_REQUESTS
+        .with_label_values(&[route, method, info.status().as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[route, method])
+        .observe(info.elapsed().as_secs_f64());
+}
+
+/// Reports the usage of the database connection pool on every scrape
+pub struct PoolCollector {
+    pool: PgPool,
+    connections: IntGaugeVec,
+}
+
+impl PoolCollector {
+    pub fn new(pool: PgPool) -> Self {
+        let connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Number of database connections by state",
+            ),
+            &["state"],
+        )
+        .unwrap();
+
+        PoolCollector { pool, connections }
+    }
+}
+
+impl Collector for PoolCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.connections.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let size = self.pool.size() as i64;
+        let idle = self.pool.num_idle() as i64;
+        self.connections.with_label_values(&["idle"]).set(idle);
+        self.connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+
+        self.connections.collect()
+    }
+}
+
+/// `GET /metrics` in the Prometheus text format
+pub async fn get_metrics() -> Result<impl warp::Reply, warp::Rejection> {
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        tracing::event!(tracing::Level::ERROR, "Cannot encode metrics: {:?}", e);
+    }
+
+    Ok(warp::reply::with_header(
+        buffer,
+        "content-type",
+        encoder.format_type(),
+    ))
+}
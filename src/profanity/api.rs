@@ -0,0 +1,372 @@
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+
+use super::{ProfanityChecker, ProfanityReport};
+use crate::metrics;
+use async_trait::async_trait;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use serde::{Deserialize, Serialize};
+
+/// Readiness probes give up after two seconds, see `routes::health`
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Error body of the bad words API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ApiResponse {
+    message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWord {
+    original: String,
+    word: String,
+    deviations: i64,
+    info: i64,
+    #[serde(rename = "replacedLen")]
+    replaced_len: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWordsResponse {
+    content: String,
+    bad_words_total: i64,
+    bad_words_list: Vec<BadWord>,
+    censored_content: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Endpoint of the bad words API, can point to a local stand-in like
+    /// the `fake-bad-words` binary
+    pub url: String,
+    /// The API key itself, preferably set through
+    /// `APP_PROFANITY_API__API_KEY` rather than in a file under version control
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key
+    pub api_key_env: String,
+    /// File to read the API key from instead of the environment
+    pub api_key_file: Option<String>,
+    pub timeout_seconds: u64,
+    /// Retries of transient failures, with exponential backoff
+    pub max_retries: u32,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            url: "https://api.apilayer.com/bad_words".to_string(),
+            api_key: None,
+            api_key_env: "BAD_WORDS_API_KEY".to_string(),
+            api_key_file: None,
+            timeout_seconds: 10,
+            max_retries: 3,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// Takes the key from `api_key_file`, `api_key` or the `api_key_env`
+    /// variable, in that order
+    pub fn api_key(&self) -> Result<String, String> {
+        match (&self.api_key_file, &self.api_key) {
+            (Some(path), _) => fs::read_to_string(path)
+                .map(|key| key.trim().to_string())
+                .map_err(|e| format!("Cannot read API key from {}: {}", path, e)),
+            (None, Some(key)) => Ok(key.clone()),
+            (None, None) => {
+                env::var(&self.api_key_env).map_err(|_| format!("{} not set", self.api_key_env))
+            }
+        }
+    }
+}
+
+/// Checks content against the apilayer.com bad words API.
+pub struct ApiLayerChecker {
+    client: ClientWithMiddleware,
+    /// Same connection pool without the retries, for the readiness probe
+    probe: reqwest::Client,
+    url: String,
+    api_key: String,
+}
+
+impl ApiLayerChecker {
+    pub fn new(config: &ApiConfig, api_key: String) -> Result<Self, reqwest::Error> {
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(config.max_retries);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+        let probe = client.clone();
+        let client = ClientBuilder::new(client)
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        Ok(ApiLayerChecker {
+            client,
+            probe,
+            url: config.url.clone(),
+            api_key,
+        })
+    }
+
+    async fn request(&self, content: String) -> Result<ProfanityReport, handle_errors::Error> {
+        let res = self
+            .client
+            .post(&self.url)
+            .query(&[("censor_character", "*")])
+            .header("apiKey", &self.api_key)
+            .body(content)
+            .send()
+            .await
+            .map_err(handle_errors::Error::MiddlwareReqwestApiError)?;
+
+        if !res.status().is_success() {
+            let status = res.status().as_u16();
+            // Gateways in front of the API don't answer with its error body
+            let body = res.text().await.unwrap_or_default();
+            let message = match serde_json::from_str::<ApiResponse>(&body) {
+                Ok(res) => res.message,
+                Err(_) => body,
+            };
+
+            let err = handle_errors::ApiLayerError { status, message };
+
+            if status < 500 {
+                return Err(handle_errors::Error::ClientError(err));
+            } else {
+                return Err(handle_errors::Error::ServerError(err));
+            }
+        }
+
+        match res.json::<BadWordsResponse>().await {
+            Ok(res) => {
+                let mut bad_words: Vec<String> = Vec::new();
+                for bad_word in res.bad_words_list {
+                    if !bad_words.contains(&bad_word.original) {
+                        bad_words.push(bad_word.original);
+                    }
+                }
+
+                Ok(ProfanityReport {
+                    content: res.content,
+                    censored_content: res.censored_content,
+                    bad_words,
+                    unchecked: false,
+                })
+            }
+            Err(e) => Err(handle_errors::Error::ReqwestApiError(e)),
+        }
+    }
+}
+
+impl std::fmt::Debug for ApiLayerChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiLayerChecker")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for ApiLayerChecker {
+    async fn check(&self, content: String) -> Result<ProfanityReport, handle_errors::Error> {
+        let started = Instant::now();
+        let result = self.request(content).await;
+
+        let outcome = match &result {
+            Ok(_) => "success",
+            Err(handle_errors::Error::ClientError(_)) => "client_error",
+            Err(handle_errors::Error::ServerError(_)) => "server_error",
+            // Connection failures, timeouts and unreadable responses
+            Err(_) => "error",
+        };
+        metrics::PROFANITY_API_REQUESTS
+            .with_label_values(&[outcome])
+            .inc();
+        metrics::PROFANITY_API_DURATION
+            .with_label_values(&[outcome])
+            .observe(started.elapsed().as_secs_f64());
+
+        result
+    }
+
+    /// Any answer means the API can be reached, the key isn't checked so
+    /// probes don't use up the quota
+    async fn ready(&self) -> Result<(), handle_errors::Error> {
+        // A single attempt which gives up well before the probe's deadline,
+        // so a slow API is reported with the actual error
+        let res = self
+            .probe
+            .head(&self.url)
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .map_err(handle_errors::Error::ReqwestApiError)?;
+
+        if res.status().is_server_error() {
+            return Err(handle_errors::Error::ServerError(
+                handle_errors::ApiLayerError {
+                    status: res.status().as_u16(),
+                    message: "Bad words API is unavailable".to_string(),
+                },
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::{
+        profanity::{fake_bad_words, ContentPolicy, DynProfanityChecker, ModerationPolicy},
+        types::moderation::ContentStatus,
+    };
+    use handle_errors::Error;
+    use warp::Filter;
+
+    const API_KEY: &str = "test-key";
+
+    /// Serves the `fake-bad-words` API on a free port and returns a client
+    /// for it, together with the number of requests the fake received
+    fn fake_api(api_key: &str, max_retries: u32) -> (ApiLayerChecker, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let routes = warp::any()
+            .map(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .untuple_one()
+            .and(fake_bad_words::routes(
+                API_KEY.to_string(),
+                fake_bad_words::wordlist(),
+            ));
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let config = ApiConfig {
+            url: format!("http://{}/bad_words", addr),
+            timeout_seconds: 1,
+            max_retries,
+            ..ApiConfig::default()
+        };
+        let checker = ApiLayerChecker::new(&config, api_key.to_string()).unwrap();
+
+        (checker, requests)
+    }
+
+    fn policy(checker: &DynProfanityChecker, policy: ModerationPolicy) -> ContentPolicy {
+        ContentPolicy::new(checker.clone(), policy, policy)
+    }
+
+    #[tokio::test]
+    async fn bad_words_are_handled_by_the_moderation_policy() {
+        let (checker, _) = fake_api(API_KEY, 0);
+        let checker: DynProfanityChecker = Arc::new(checker);
+
+        match policy(&checker, ModerationPolicy::Reject)
+            .moderate_answer("you arse".to_string())
+            .await
+        {
+            Err(Error::InappropriateContent(words)) => assert_eq!(words, ["arse"]),
+            other => panic!("expected the answer to be rejected, got {:?}", other),
+        }
+
+        let censored = policy(&checker, ModerationPolicy::Censor)
+            .moderate_answer("you arse".to_string())
+            .await
+            .unwrap();
+        assert_eq!(censored.content, "you ****");
+        assert_eq!(censored.status, ContentStatus::Published);
+
+        let held = policy(&checker, ModerationPolicy::Review)
+            .moderate_answer("you arse".to_string())
+            .await
+            .unwrap();
+        assert_eq!(held.content, "you arse");
+        assert_eq!(held.status, ContentStatus::Pending);
+
+        let clean = policy(&checker, ModerationPolicy::Reject)
+            .moderate_answer("you there".to_string())
+            .await
+            .unwrap();
+        assert_eq!(clean.content, "you there");
+        assert_eq!(clean.status, ContentStatus::Published);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_reported_without_retrying() {
+        let (checker, requests) = fake_api(API_KEY, 2);
+
+        match checker.check("__client_error__".to_string()).await {
+            Err(Error::ClientError(e)) => {
+                assert_eq!(e.status, 400);
+                assert_eq!(e.message, "Bad request");
+            }
+            other => panic!("expected a client error, got {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn wrong_api_key_is_a_client_error() {
+        let (checker, _) = fake_api("wrong-key", 0);
+
+        match checker.check("you there".to_string()).await {
+            Err(Error::ClientError(e)) => assert_eq!(e.status, 401),
+            other => panic!("expected a client error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_before_being_reported() {
+        let (checker, requests) = fake_api(API_KEY, 1);
+
+        match checker.check("__server_error__".to_string()).await {
+            Err(Error::ServerError(e)) => {
+                assert_eq!(e.status, 500);
+                assert_eq!(e.message, "Something went wrong");
+            }
+            other => panic!("expected a server error, got {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn slow_answers_run_into_the_timeout() {
+        let (checker, _) = fake_api(API_KEY, 0);
+
+        match checker.check("__slow__".to_string()).await {
+            Err(Error::MiddlwareReqwestApiError(reqwest_middleware::Error::Reqwest(e))) => {
+                assert!(e.is_timeout())
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn readiness_only_needs_the_api_to_answer() {
+        let (checker, requests) = fake_api(API_KEY, 0);
+        assert!(checker.ready().await.is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let config = ApiConfig {
+            url: "http://127.0.0.1:1/bad_words".to_string(),
+            ..ApiConfig::default()
+        };
+        let unreachable = ApiLayerChecker::new(&config, API_KEY.to_string()).unwrap();
+        assert!(matches!(
+            unreachable.ready().await,
+            Err(Error::ReqwestApiError(_))
+        ));
+    }
+}
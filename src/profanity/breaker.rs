@@ -0,0 +1,415 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::{DynProfanityChecker, ProfanityChecker, ProfanityReport};
+use crate::metrics;
+use async_trait::async_trait;
+use handle_errors::Error;
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+/// How content is treated while the circuit is open
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FailMode {
+    /// Accept content unchecked
+    Open,
+    /// Refuse content until the upstream service recovered
+    #[default]
+    Closed,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BreakerConfig {
+    /// Share of failed checks within the window which opens the circuit
+    pub error_threshold: f64,
+    /// Number of most recent checks the error rate is taken from
+    pub window: usize,
+    /// How long the circuit stays open before a trial check is let through
+    pub cooldown_seconds: u64,
+    pub fail: FailMode,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            error_threshold: 0.5,
+            window: 20,
+            cooldown_seconds: 30,
+            fail: FailMode::Closed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// A single trial check decides whether the circuit closes again
+    HalfOpen,
+}
+
+impl CircuitState {
+    const ALL: [CircuitState; 3] = [
+        CircuitState::Closed,
+        CircuitState::Open,
+        CircuitState::HalfOpen,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+
+    /// Sets the state gauge to 1 for this state and 0 for the others
+    fn publish(self) {
+        for state in CircuitState::ALL {
+            metrics::PROFANITY_CIRCUIT_STATE
+                .with_label_values(&[state.as_str()])
+                .set(i64::from(state == self));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    /// Whether the most recent checks succeeded, newest last
+    outcomes: VecDeque<bool>,
+    /// When the circuit opened or the last trial check started
+    since: Instant,
+}
+
+impl Circuit {
+    fn change_to(&mut self, state: CircuitState) {
+        self.state = state;
+        self.since = Instant::now();
+        state.publish();
+        metrics::PROFANITY_CIRCUIT_TRANSITIONS
+            .with_label_values(&[state.as_str()])
+            .inc();
+    }
+}
+
+/// Stops calling a failing checker once its error rate crosses the
+/// threshold, instead of letting every post wait for it to time out.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    inner: DynProfanityChecker,
+    config: BreakerConfig,
+    circuit: Mutex<Circuit>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: DynProfanityChecker, config: BreakerConfig) -> Self {
+        CircuitState::Closed.publish();
+        CircuitBreaker {
+            inner,
+            circuit: Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::with_capacity(config.window),
+                since: Instant::now(),
+            }),
+            config,
+        }
+    }
+
+    /// Whether a check may be sent to the inner checker right now
+    fn allow(&self) -> bool {
+        let mut circuit = self.circuit.lock();
+        let cooldown = Duration::from_secs(self.config.cooldown_seconds);
+
+        match circuit.state {
+            CircuitState::Closed => true,
+            // A trial which never reported back, e.g. because the request
+            // was cancelled, doesn't keep the circuit half open forever
+            CircuitState::Open | CircuitState::HalfOpen if circuit.since.elapsed() >= cooldown => {
+                if circuit.state == CircuitState::Open {
+                    tracing::event!(
+                        tracing::Level::INFO,
+                        circuit = CircuitState::HalfOpen.as_str(),
+                        "profanity API circuit half open, sending trial check"
+                    );
+                    circuit.change_to(CircuitState::HalfOpen);
+                } else {
+                    circuit.since = Instant::now();
+                }
+                true
+            }
+            CircuitState::Open | CircuitState::HalfOpen => false,
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut circuit = self.circuit.lock();
+
+        match circuit.state {
+            CircuitState::HalfOpen if success => {
+                circuit.change_to(CircuitState::Closed);
+                circuit.outcomes.clear();
+                tracing::event!(
+                    tracing::Level::INFO,
+                    circuit = CircuitState::Closed.as_str(),
+                    "profanity API recovered, circuit closed"
+                );
+            }
+            CircuitState::HalfOpen => {
+                circuit.change_to(CircuitState::Open);
+                tracing::event!(
+                    tracing::Level::WARN,
+                    circuit = CircuitState::Open.as_str(),
+                    "profanity API trial check failed, circuit open again"
+                );
+            }
+            CircuitState::Closed => {
+                circuit.outcomes.push_back(success);
+                while circuit.outcomes.len() > self.config.window {
+                    circuit.outcomes.pop_front();
+                }
+
+                let failures = circuit.outcomes.iter().filter(|success| !**success).count();
+                let error_rate = failures as f64 / circuit.outcomes.len() as f64;
+                if circuit.outcomes.len() >= self.config.window
+                    && error_rate >= self.config.error_threshold
+                {
+                    circuit.change_to(CircuitState::Open);
+                    tracing::event!(
+                        tracing::Level::WARN,
+                        circuit = CircuitState::Open.as_str(),
+                        error_rate,
+                        "profanity API is failing, circuit open"
+                    );
+                }
+            }
+            // Checks started before the circuit opened don't count
+            CircuitState::Open => {}
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for CircuitBreaker {
+    async fn check(&self, content: String) -> Result<ProfanityReport, Error> {
+        if !self.allow() {
+            return match self.config.fail {
+                FailMode::Open => {
+                    tracing::event!(
+                        tracing::Level::WARN,
+                        "profanity API circuit open, accepting content unchecked"
+                    );
+                    Ok(ProfanityReport {
+                        censored_content: content.clone(),
+                        content,
+                        bad_words: vec![],
+                        unchecked: true,
+                    })
+                }
+                FailMode::Closed => Err(Error::ProfanityCheckUnavailable),
+            };
+        }
+
+        match self.inner.check(content).await {
+            Ok(report) => {
+                self.record(true);
+                Ok(report)
+            }
+            Err(e) => {
+                self.record(false);
+                Err(e)
+            }
+        }
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        if self.circuit.lock().state == CircuitState::Open {
+            return Err(Error::ProfanityCheckUnavailable);
+        }
+
+        self.inner.ready().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use handle_errors::ApiLayerError;
+
+    /// Fails while `failing` is set and counts the checks reaching it
+    #[derive(Debug, Default)]
+    struct Upstream {
+        failing: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ProfanityChecker for Upstream {
+        async fn check(&self, content: String) -> Result<ProfanityReport, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(Error::ServerError(ApiLayerError {
+                    status: 503,
+                    message: "unavailable".to_string(),
+                }));
+            }
+
+            Ok(ProfanityReport {
+                censored_content: content.clone(),
+                content,
+                bad_words: vec![],
+                unchecked: false,
+            })
+        }
+    }
+
+    impl Upstream {
+        fn fail(&self, failing: bool) {
+            self.failing.store(failing, Ordering::SeqCst);
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    fn breaker(upstream: &Arc<Upstream>, fail: FailMode) -> CircuitBreaker {
+        CircuitBreaker::new(
+            upstream.clone(),
+            BreakerConfig {
+                error_threshold: 0.5,
+                window: 4,
+                cooldown_seconds: 60,
+                fail,
+            },
+        )
+    }
+
+    impl CircuitBreaker {
+        fn state(&self) -> CircuitState {
+            self.circuit.lock().state
+        }
+
+        fn end_cooldown(&self) {
+            self.circuit.lock().since -= Duration::from_secs(60);
+        }
+
+        async fn open(&self, upstream: &Upstream) {
+            upstream.fail(true);
+            for _ in 0..4 {
+                let _ = self.check("text".to_string()).await;
+            }
+            assert_eq!(self.state(), CircuitState::Open);
+        }
+    }
+
+    #[tokio::test]
+    async fn circuit_opens_once_the_error_rate_reaches_the_threshold() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+
+        for failing in [false, false, false, true] {
+            upstream.fail(failing);
+            let _ = breaker.check("text".to_string()).await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let _ = breaker.check("text".to_string()).await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let calls = upstream.calls();
+        let _ = breaker.check("text".to_string()).await;
+        assert_eq!(upstream.calls(), calls);
+    }
+
+    #[tokio::test]
+    async fn too_few_checks_dont_open_the_circuit() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+        upstream.fail(true);
+
+        for _ in 0..3 {
+            let _ = breaker.check("text".to_string()).await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn successful_trial_check_closes_the_circuit() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+        breaker.open(&upstream).await;
+
+        breaker.end_cooldown();
+        upstream.fail(false);
+        assert!(breaker.check("text".to_string()).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.ready().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn failed_trial_check_opens_the_circuit_again() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+        breaker.open(&upstream).await;
+
+        breaker.end_cooldown();
+        let calls = upstream.calls();
+        assert!(breaker.check("text".to_string()).await.is_err());
+        assert_eq!(upstream.calls(), calls + 1);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // The cooldown starts over
+        let _ = breaker.check("text".to_string()).await;
+        assert_eq!(upstream.calls(), calls + 1);
+    }
+
+    #[tokio::test]
+    async fn only_one_trial_check_is_let_through() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+        breaker.open(&upstream).await;
+
+        breaker.end_cooldown();
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.allow());
+    }
+
+    #[tokio::test]
+    async fn open_circuit_refuses_content_when_failing_closed() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Closed);
+        breaker.open(&upstream).await;
+
+        assert!(matches!(
+            breaker.check("text".to_string()).await,
+            Err(Error::ProfanityCheckUnavailable)
+        ));
+        assert!(matches!(
+            breaker.ready().await,
+            Err(Error::ProfanityCheckUnavailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn open_circuit_accepts_content_unchecked_when_failing_open() {
+        let upstream = Arc::new(Upstream::default());
+        let breaker = breaker(&upstream, FailMode::Open);
+        breaker.open(&upstream).await;
+
+        let report = breaker.check("text".to_string()).await.unwrap();
+        assert!(report.unchecked);
+        assert_eq!(report.censored_content, "text");
+        assert!(report.bad_words.is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use super::{DynProfanityChecker, ProfanityChecker, ProfanityReport};
+use async_trait::async_trait;
+use handle_errors::Error;
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub ttl_seconds: u64,
+    /// Zero disables the cache
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            ttl_seconds: 3600,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Remembers the results of another checker keyed by a hash of the
+/// content, so the same text isn't checked again until the entry expired.
+pub struct CachedChecker {
+    inner: DynProfanityChecker,
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<u64, (Instant, ProfanityReport)>>,
+}
+
+impl CachedChecker {
+    pub fn new(inner: DynProfanityChecker, config: CacheConfig) -> Self {
+        CachedChecker {
+            inner,
+            ttl: Duration::from_secs(config.ttl_seconds),
+            max_entries: config.max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lookup(&self, key: u64, content: &str) -> Option<ProfanityReport> {
+        let entries = self.entries.lock();
+        match entries.get(&key) {
+            // Comparing the content rules out hash collisions
+            Some((cached_on, report))
+                if cached_on.elapsed() < self.ttl && report.content == content =>
+            {
+                Some(report.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn insert(&self, key: u64, report: ProfanityReport) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, (cached_on, _)| cached_on.elapsed() < self.ttl);
+        }
+        if entries.len() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (cached_on, _))| *cached_on)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (Instant::now(), report));
+    }
+}
+
+impl std::fmt::Debug for CachedChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedChecker")
+            .field("inner", &self.inner)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[async_trait]
+impl ProfanityChecker for CachedChecker {
+    async fn check(&self, content: String) -> Result<ProfanityReport, Error> {
+        let key = content_hash(&content);
+        if let Some(report) = self.lookup(key, &content) {
+            tracing::event!(tracing::Level::DEBUG, "profanity cache hit");
+            return Ok(report);
+        }
+
+        let report = self.inner.check(content).await?;
+        // Content let through while the API is down is checked again next time
+        if !report.unchecked {
+            self.insert(key, report.clone());
+        }
+
+        Ok(report)
+    }
+
+    async fn ready(&self) -> Result<(), Error> {
+        self.inner.ready().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// Counts the checks reaching it, reports every content as `unchecked`
+    /// or not
+    #[derive(Debug, Default)]
+    struct Upstream {
+        unchecked: bool,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ProfanityChecker for Upstream {
+        async fn check(&self, content: String) -> Result<ProfanityReport, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(ProfanityReport {
+                censored_content: content.clone(),
+                content,
+                bad_words: vec![],
+                unchecked: self.unchecked,
+            })
+        }
+    }
+
+    async fn calls_for(upstream: Upstream, config: CacheConfig, contents: &[&str]) -> usize {
+        let upstream = Arc::new(upstream);
+        let cache = CachedChecker::new(upstream.clone(), config);
+        for content in contents {
+            assert_eq!(
+                cache.check(content.to_string()).await.unwrap().content,
+                *content
+            );
+        }
+
+        upstream.calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn repeated_content_is_answered_from_the_cache() {
+        let calls = calls_for(
+            Upstream::default(),
+            CacheConfig::default(),
+            &["a", "b", "a"],
+        )
+        .await;
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_checked_again() {
+        let config = CacheConfig {
+            ttl_seconds: 0,
+            ..CacheConfig::default()
+        };
+        assert_eq!(calls_for(Upstream::default(), config, &["a", "a"]).await, 2);
+    }
+
+    #[tokio::test]
+    async fn unchecked_reports_are_not_cached() {
+        let upstream = Upstream {
+            unchecked: true,
+            ..Upstream::default()
+        };
+        assert_eq!(
+            calls_for(upstream, CacheConfig::default(), &["a", "a"]).await,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn oldest_entry_is_evicted_when_the_cache_is_full() {
+        let config = CacheConfig {
+            max_entries: 1,
+            ..CacheConfig::default()
+        };
+        assert_eq!(
+            calls_for(Upstream::default(), config, &["a", "b", "b", "a"]).await,
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_entries_disable_the_cache() {
+        let config = CacheConfig {
+            max_entries: 0,
+            ..CacheConfig::default()
+        };
+        assert_eq!(calls_for(Upstream::default(), config, &["a", "a"]).await, 2);
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use handle_errors::Error;
+
+pub mod api;
+pub mod breaker;
+pub mod cache;
+pub mod policy;
+pub mod wordlist;
+
+/// The `fake-bad-words` binary, served in-process by the tests
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../bin/fake_bad_words.rs"]
+pub mod fake_bad_words;
+
+pub use api::{ApiConfig, ApiLayerChecker};
+pub use breaker::{BreakerConfig, CircuitBreaker};
+pub use cache::{CacheConfig, CachedChecker};
+pub use policy::{ContentPolicy, ModerationPolicy};
+pub use wordlist::WordlistChecker;
+
+/// Shared handle to whichever profanity checker the server was started
+/// with. This is what gets injected into the route handlers.
+pub type DynProfanityChecker = Arc<dyn ProfanityChecker>;
+
+/// Result of checking a piece of content for bad words
+#[derive(Debug, Clone)]
+pub struct ProfanityReport {
+    /// The content as it was submitted
+    pub content: String,
+    /// The content with every bad word replaced by `*`
+    pub censored_content: String,
+    /// The offending words as they appear in the content
+    pub bad_words: Vec<String>,
+    /// Set when the content was let through without being checked, so the
+    /// report says nothing about it and must not be remembered
+    pub unchecked: bool,
+}
+
+#[async_trait]
+pub trait ProfanityChecker: Send + Sync + Debug {
+    async fn check(&self, content: String) -> Result<ProfanityReport, Error>;
+
+    /// Whether content can be checked right now, for the readiness probe
+    async fn ready(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
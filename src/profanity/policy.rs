@@ -0,0 +1,110 @@
+use handle_errors::Error;
+use serde::Deserialize;
+
+use super::{DynProfanityChecker, ProfanityReport};
+use crate::types::moderation::ContentStatus;
+
+/// What happens to a post containing bad words
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationPolicy {
+    /// Refuse the post, telling the author which words were found
+    Reject,
+    /// Replace the bad words with `*` and publish the post
+    #[default]
+    Censor,
+    /// Keep the post as written, but hold it until a moderator reviewed it
+    Review,
+}
+
+/// Content after the moderation policy has been applied
+#[derive(Debug, Clone)]
+pub struct Moderated<T> {
+    pub content: T,
+    pub status: ContentStatus,
+}
+
+/// Applies the configured [`ModerationPolicy`] for each content type to
+/// posted questions and answers.
+#[derive(Debug, Clone)]
+pub struct ContentPolicy {
+    checker: DynProfanityChecker,
+    questions: ModerationPolicy,
+    answers: ModerationPolicy,
+}
+
+impl ContentPolicy {
+    pub fn new(
+        checker: DynProfanityChecker,
+        questions: ModerationPolicy,
+        answers: ModerationPolicy,
+    ) -> Self {
+        ContentPolicy {
+            checker,
+            questions,
+            answers,
+        }
+    }
+
+    pub async fn moderate_question(
+        &self,
+        title: String,
+        content: String,
+    ) -> Result<Moderated<(String, String)>, Error> {
+        let (title, content) = tokio::join!(self.checker.check(title), self.checker.check(content));
+        let (title, content) = (title?, content?);
+
+        let status = apply(self.questions, &[&title, &content])?;
+        Ok(Moderated {
+            content: (
+                moderated_text(self.questions, title),
+                moderated_text(self.questions, content),
+            ),
+            status,
+        })
+    }
+
+    /// Whether posts can be checked right now
+    pub async fn ready(&self) -> Result<(), Error> {
+        self.checker.ready().await
+    }
+
+    pub async fn moderate_answer(&self, content: String) -> Result<Moderated<String>, Error> {
+        let content = self.checker.check(content).await?;
+
+        let status = apply(self.answers, &[&content])?;
+        Ok(Moderated {
+            content: moderated_text(self.answers, content),
+            status,
+        })
+    }
+}
+
+/// Decide whether the checked texts of a post are published, held for
+/// review or rejected.
+fn apply(policy: ModerationPolicy, reports: &[&ProfanityReport]) -> Result<ContentStatus, Error> {
+    let mut bad_words: Vec<String> = Vec::new();
+    for word in reports.iter().flat_map(|report| &report.bad_words) {
+        if !bad_words.contains(word) {
+            bad_words.push(word.clone());
+        }
+    }
+
+    if bad_words.is_empty() {
+        return Ok(ContentStatus::Published);
+    }
+
+    match policy {
+        ModerationPolicy::Reject => Err(Error::InappropriateContent(bad_words)),
+        ModerationPolicy::Censor => Ok(ContentStatus::Published),
+        ModerationPolicy::Review => Ok(ContentStatus::Pending),
+    }
+}
+
+/// Only censoring rewrites the text, held posts are reviewed as written.
+fn moderated_text(policy: ModerationPolicy, report: ProfanityReport) -> String {
+    match policy {
+        ModerationPolicy::Censor => report.censored_content,
+        ModerationPolicy::Reject | ModerationPolicy::Review => report.content,
+    }
+}
@@ -0,0 +1,206 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use super::{ProfanityChecker, ProfanityReport};
+use async_trait::async_trait;
+
+/// Checks content against a local list of bad words, so no external
+/// service is needed. Words are matched as a whole after undoing common
+/// obfuscations like `sh1t`, `$hit`, `s.h.i.t` or `shiiit`.
+#[derive(Debug, Clone)]
+pub struct WordlistChecker {
+    words: HashSet<String>,
+}
+
+impl WordlistChecker {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        WordlistChecker {
+            words: words
+                .into_iter()
+                .map(|word| normalize(word.as_ref()))
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Read a wordlist with one word per line. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let list = fs::read_to_string(path)?;
+
+        Ok(WordlistChecker::new(
+            list.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    fn is_bad(&self, word: &str) -> bool {
+        let word = normalize(word);
+        !word.is_empty()
+            && (self.words.contains(&word)
+                || self.words.contains(&squash(&word, 2))
+                || self.words.contains(&squash(&word, 1)))
+    }
+
+    fn check_text(&self, content: String) -> ProfanityReport {
+        let mut censored = String::with_capacity(content.len());
+        let mut bad_words: Vec<String> = Vec::new();
+        let mut token = String::new();
+
+        let mut flush = |token: &mut String, censored: &mut String| {
+            match self.bad_word(token) {
+                Some(word) => {
+                    let start = token.find(word).unwrap_or_default();
+                    censored.push_str(&token[..start]);
+                    censored.push_str(&"*".repeat(word.chars().count()));
+                    censored.push_str(&token[start + word.len()..]);
+                    if !bad_words.iter().any(|bad_word| bad_word == word) {
+                        bad_words.push(word.to_string());
+                    }
+                }
+                None => censored.push_str(token),
+            }
+            token.clear();
+        };
+
+        for c in content.chars() {
+            if c.is_whitespace() {
+                flush(&mut token, &mut censored);
+                censored.push(c);
+            } else {
+                token.push(c);
+            }
+        }
+        flush(&mut token, &mut censored);
+
+        ProfanityReport {
+            content,
+            censored_content: censored,
+            bad_words,
+            unchecked: false,
+        }
+    }
+
+    /// The bad word in a whitespace delimited token, without surrounding
+    /// punctuation like in `"damn!"`.
+    fn bad_word<'t>(&self, token: &'t str) -> Option<&'t str> {
+        let core = token.trim_matches(|c: char| !c.is_alphanumeric() && !is_leet(c));
+        if core.is_empty() || !self.is_bad(core) {
+            None
+        } else {
+            Some(core)
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for WordlistChecker {
+    async fn check(&self, content: String) -> Result<ProfanityReport, handle_errors::Error> {
+        Ok(self.check_text(content))
+    }
+}
+
+/// Characters commonly used in place of letters
+fn is_leet(c: char) -> bool {
+    matches!(c, '@' | '$')
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        c => c,
+    }
+}
+
+/// Lowercase, undo leetspeak and drop everything which isn't a letter, so
+/// `S.h-1_t` becomes `shit`.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .map(unleet)
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Shorten runs of the same letter to at most `max` letters.
+fn squash(word: &str, max: usize) -> String {
+    let mut squashed = String::with_capacity(word.len());
+    let mut last = None;
+    let mut run = 0;
+
+    for c in word.chars() {
+        if Some(c) == last {
+            run += 1;
+        } else {
+            last = Some(c);
+            run = 1;
+        }
+        if run <= max {
+            squashed.push(c);
+        }
+    }
+
+    squashed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> WordlistChecker {
+        WordlistChecker::new(["shit", "ass", "damn"])
+    }
+
+    fn bad_words(content: &str) -> Vec<String> {
+        checker().check_text(content.to_string()).bad_words
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        assert_eq!(bad_words("SHIT Shit"), ["SHIT", "Shit"]);
+    }
+
+    #[test]
+    fn matching_undoes_leetspeak() {
+        assert_eq!(
+            bad_words("sh1t $hit a$$ d4mn"),
+            ["sh1t", "$hit", "a$$", "d4mn"]
+        );
+    }
+
+    #[test]
+    fn matching_ignores_separators_and_repeated_letters() {
+        assert_eq!(bad_words("s.h.i.t S-h_1-t"), ["s.h.i.t", "S-h_1-t"]);
+        assert_eq!(bad_words("shiiiit daaamn"), ["shiiiit", "daaamn"]);
+    }
+
+    #[test]
+    fn only_whole_words_match() {
+        assert!(bad_words("class assess bass passage damnation shitake").is_empty());
+    }
+
+    #[test]
+    fn censoring_keeps_surrounding_punctuation() {
+        let report = checker().check_text("Oh \"damn!\" that sh1t.".to_string());
+
+        assert_eq!(report.censored_content, "Oh \"****!\" that ****.");
+        assert_eq!(report.bad_words, ["damn", "sh1t"]);
+        assert!(!report.unchecked);
+    }
+
+    #[test]
+    fn words_repeated_in_a_text_are_reported_once() {
+        assert_eq!(bad_words("damn it, damn"), ["damn"]);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    store::DynStore,
+    types::account::{RoleUpdate, Session},
+};
+use tracing::{event, instrument, Level};
+
+#[instrument]
+pub async fn get_accounts(
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_accounts().await {
+        Ok(accounts) => Ok(warp::reply::json(&accounts)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn set_account_role(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    update: RoleUpdate,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "test_warp",
+        Level::INFO,
+        "account {} sets role of account {} to {}",
+        session.account_id.0,
+        id,
+        update.role
+    );
+
+    match store.set_account_role(id, update.role).await {
+        Ok(account) => Ok(warp::reply::json(&account)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn delete_account(
+    id: i32,
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "test_warp",
+        Level::INFO,
+        "account {} deletes account {}",
+        session.account_id.0,
+        id
+    );
+
+    match store.delete_account(id).await {
+        Ok(true) => Ok(warp::reply::json(&id)),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::NotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
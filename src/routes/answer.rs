@@ -1,12 +1,154 @@
-use crate::{store::Store, types::answer::NewAnswer};
-use warp::hyper::StatusCode;
+use crate::{
+    profanity::ContentPolicy,
+    routes::{
+        format::{self, AnswerRow, ListFormat},
+        status_code,
+    },
+    store::DynStore,
+    types::{
+        account::{Session, Viewer},
+        answer::{Answer, AnswerUpdate, NewAnswer},
+        moderation::{ContentKind, ContentRef, ContentStatus},
+    },
+};
+use futures::StreamExt;
+use tracing::{event, instrument, Level};
+use warp::Reply;
+
+#[instrument]
+pub async fn get_answers(
+    question_id: i32,
+    accept: Option<String>,
+    session: Option<Session>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "test_warp", Level::INFO, "querying answers of question {}", question_id);
+    let list_format = format::negotiate(accept.as_deref())?;
+    let viewer = Viewer::from(session);
+    if let Err(e) = store.get_question(question_id, &viewer).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    if list_format == ListFormat::Json {
+        return match store.get_answers(question_id, &viewer).await {
+            Ok(answers) => Ok(warp::reply::json(&answers).into_response()),
+            Err(e) => Err(warp::reject::custom(e)),
+        };
+    }
+
+    let answers = store.stream_answers(question_id, viewer);
+    Ok(match list_format {
+        ListFormat::Csv => format::csv(
+            answers.map(|answer| answer.map(AnswerRow::from)),
+            AnswerRow::COLUMNS,
+            "answers.csv",
+        ),
+        _ => format::ndjson(answers),
+    })
+}
 
 pub async fn add_answer(
-    store: Store,
+    session: Session,
+    store: DynStore,
+    policy: ContentPolicy,
     answer: NewAnswer,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.add_answer(answer).await {
-        Ok(_) => Ok(warp::reply::with_status("Answer added", StatusCode::OK)),
+    // Questions held for review can't be answered by others yet
+    let viewer = Viewer::from(Some(session.clone()));
+    if let Err(e) = store.get_question(answer.question_id.0, &viewer).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    let moderated = match policy.moderate_answer(answer.content).await {
+        Ok(moderated) => moderated,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let answer = NewAnswer {
+        content: moderated.content,
+        question_id: answer.question_id,
+    };
+
+    match store
+        .add_answer(answer, session.account_id, moderated.status)
+        .await
+    {
+        Ok(answer) => Ok(warp::reply::with_status(
+            warp::reply::json(&answer),
+            status_code(moderated.status),
+        )),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+pub async fn update_answer(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    policy: ContentPolicy,
+    update: AnswerUpdate,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if session.can_moderate() || store.is_answer_owner(id, &session.account_id).await? {
+        let viewer = Viewer::from(Some(session.clone()));
+        let answer = match store.get_answer(id, &viewer).await {
+            Ok(answer) => answer,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+
+        let moderated = match policy.moderate_answer(update.content).await {
+            Ok(moderated) => moderated,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+
+        // Content taken down by a moderator stays hidden when it's edited
+        let status = match store
+            .get_content_status(ContentRef {
+                kind: ContentKind::Answer,
+                id,
+            })
+            .await?
+        {
+            ContentStatus::Hidden => ContentStatus::Hidden,
+            _ => moderated.status,
+        };
+
+        let answer = Answer {
+            content: moderated.content,
+            ..answer
+        };
+
+        match store.update_answer(answer, status).await {
+            Ok(answer) => Ok(warp::reply::with_status(
+                warp::reply::json(&answer),
+                status_code(status),
+            )),
+            Err(e) => Err(warp::reject::custom(e)),
+        }
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
+
+pub async fn delete_answer(
+    id: i32,
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Missing content is reported as such rather than as not being owned
+    store
+        .get_content_status(ContentRef {
+            kind: ContentKind::Answer,
+            id,
+        })
+        .await?;
+
+    if session.can_moderate() || store.is_answer_owner(id, &session.account_id).await? {
+        match store.delete_answer(id).await {
+            Ok(true) => Ok(warp::reply::json(&id)),
+            Ok(false) => Err(warp::reject::custom(handle_errors::Error::NotFound)),
+            Err(e) => Err(warp::reject::custom(e)),
+        }
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
@@ -1,22 +1,30 @@
-use std::{env, future};
+use std::future;
 
 use crate::{
-    store::Store,
-    types::account::{Account, AccountId, Session},
+    metrics,
+    store::DynStore,
+    types::account::{Account, AccountId, RefreshRequest, Role, Session, TokenKind, TokenPair},
 };
 use argon2::Config;
 use chrono::prelude::*;
+use once_cell::sync::OnceCell;
 use rand::Rng;
 use reqwest::StatusCode;
+use uuid::Uuid;
 use warp::Filter;
 
-pub async fn register(store: Store, account: Account) -> Result<impl warp::Reply, warp::Rejection> {
+pub async fn register(
+    store: DynStore,
+    account: Account,
+) -> Result<impl warp::Reply, warp::Rejection> {
     let hashed_password = hash(account.password.as_bytes());
 
+    // Elevated roles can only be granted by an admin.
     let account = Account {
         id: account.id,
         email: account.email,
         password: hashed_password,
+        role: Role::User,
     };
 
     match store.add_account(account).await {
@@ -31,13 +39,14 @@ pub fn hash(password: &[u8]) -> String {
     argon2::hash_encoded(password, &salt, &config).unwrap()
 }
 
-pub async fn login(store: Store, login: Account) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.get_account(login.email).await {
+pub async fn login(store: DynStore, login: Account) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = match store.get_account(login.email).await {
         Ok(account) => match verify_password(&account.password, login.password.as_bytes()) {
             Ok(verified) => {
                 if verified {
-                    Ok(warp::reply::json(&issue_token(
+                    Ok(warp::reply::json(&issue_tokens(
                         account.id.expect("id not found"),
+                        account.role,
                     )))
                 } else {
                     Err(warp::reject::custom(handle_errors::Error::WrongPassword))
@@ -47,34 +56,144 @@ pub async fn login(store: Store, login: Account) -> Result<impl warp::Reply, war
                 handle_errors::Error::ArgonLibraryError(e),
             )),
         },
+        // Don't reveal which e-mail addresses have an account
+        Err(handle_errors::Error::NotFound) => {
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        }
         Err(e) => Err(warp::reject::custom(e)),
-    }
+    };
+
+    let outcome = match &result {
+        Ok(_) => "success",
+        Err(e) => match e.find() {
+            Some(handle_errors::Error::WrongPassword) => "wrong_credentials",
+            _ => "error",
+        },
+    };
+    metrics::LOGINS.with_label_values(&[outcome]).inc();
+
+    result
 }
 
 fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
-fn issue_token(account_id: AccountId) -> String {
-    let key = env::var("PASETO_TOKEN").unwrap();
-    let current_date_time = Utc::now();
-    let dt = current_date_time + chrono::Duration::days(1);
+/// Access tokens are short-lived; clients renew them with the refresh token.
+const ACCESS_TOKEN_MINUTES: i64 = 15;
+const REFRESH_TOKEN_DAYS: i64 = 7;
+
+pub async fn refresh(
+    store: DynStore,
+    request: RefreshRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let session = match verify_token(request.refresh_token) {
+        Ok(session) if session.kind == TokenKind::Refresh => session,
+        _ => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::CannotDecryptToken,
+            ))
+        }
+    };
+
+    // Pick up role changes and refuse deleted accounts
+    let account = match store.get_account_by_id(session.account_id.0).await {
+        Ok(account) => account,
+        Err(handle_errors::Error::NotFound) => {
+            return Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+        }
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    // Refresh tokens are single use, the old login is replaced by a new one.
+    // Only the request which revokes it gets new tokens, so replays racing
+    // each other can't both succeed.
+    match store.revoke_session(&session.sid, session.exp).await {
+        Ok(true) => Ok(warp::reply::json(&issue_tokens(
+            session.account_id,
+            account.role,
+        ))),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::TokenRevoked)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn logout(
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Keep the entry until the refresh token of this login expires as well
+    let expires = session.nbf + chrono::Duration::days(REFRESH_TOKEN_DAYS);
+
+    match store.revoke_session(&session.sid, expires).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            warp::reply(),
+            StatusCode::NO_CONTENT,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+fn issue_tokens(account_id: AccountId, role: Role) -> TokenPair {
+    let sid = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let expires = now + chrono::Duration::minutes(ACCESS_TOKEN_MINUTES);
 
+    TokenPair {
+        access_token: issue_token(&account_id, role, &sid, TokenKind::Access, now, expires),
+        refresh_token: issue_token(
+            &account_id,
+            role,
+            &sid,
+            TokenKind::Refresh,
+            now,
+            now + chrono::Duration::days(REFRESH_TOKEN_DAYS),
+        ),
+        expires,
+    }
+}
+
+/// Key tokens are encrypted with, set once at startup
+static PASETO_KEY: OnceCell<String> = OnceCell::new();
+
+pub fn set_paseto_key(key: String) {
+    if PASETO_KEY.set(key).is_err() {
+        tracing::warn!("PASETO key was already set, keeping the first one");
+    }
+}
+
+fn paseto_key() -> &'static [u8] {
+    PASETO_KEY
+        .get()
+        .expect("PASETO key must be set before issuing tokens")
+        .as_bytes()
+}
+
+fn issue_token(
+    account_id: &AccountId,
+    role: Role,
+    sid: &str,
+    kind: TokenKind,
+    not_before: DateTime<Utc>,
+    expires: DateTime<Utc>,
+) -> String {
     paseto::tokens::PasetoBuilder::new()
-        .set_encryption_key(&Vec::from(key.as_bytes()))
-        .set_expiration(&dt)
-        .set_not_before(&Utc::now())
+        .set_encryption_key(&Vec::from(paseto_key()))
+        .set_expiration(&expires)
+        .set_not_before(&not_before)
         .set_claim("account_id", serde_json::json!(account_id))
+        .set_claim("role", serde_json::json!(role))
+        .set_claim("sid", serde_json::json!(sid))
+        .set_claim("kind", serde_json::json!(kind))
         .build()
         .expect("Failed to construct paseto token w/ builder!")
 }
 
 pub fn verify_token(token: String) -> Result<Session, handle_errors::Error> {
-    let key = env::var("PASETO_KEY").unwrap();
     let token = paseto::tokens::validate_local_token(
         &token,
         None,
-        key.as_bytes(),
+        paseto_key(),
         &paseto::tokens::TimeBackend::Chrono,
     )
     .map_err(|_| handle_errors::Error::CannotDecryptToken)?;
@@ -82,13 +201,59 @@ pub fn verify_token(token: String) -> Result<Session, handle_errors::Error> {
     serde_json::from_value::<Session>(token).map_err(|_| handle_errors::Error::CannotDecryptToken)
 }
 
-pub fn auth() -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
-    warp::header::<String>("Authorization").and_then(|token: String| {
-        let token = match verify_token(token) {
-            Ok(t) => t,
-            Err(_) => return future::ready(Err(warp::reject::reject())),
-        };
+async fn authenticate(token: String, store: DynStore) -> Result<Session, warp::Rejection> {
+    let session = match verify_token(token) {
+        Ok(session) if session.kind == TokenKind::Access => session,
+        _ => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::CannotDecryptToken,
+            ))
+        }
+    };
+
+    match store.is_session_revoked(&session.sid).await {
+        Ok(false) => Ok(session),
+        Ok(true) => Err(warp::reject::custom(handle_errors::Error::TokenRevoked)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Extracts the [`Session`] of a valid, unrevoked access token from the
+/// `Authorization` header.
+pub fn auth(store: DynStore) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::header::<String>("Authorization")
+        .and(warp::any().map(move || store.clone()))
+        .and_then(authenticate)
+}
+
+/// Like [`auth`] for routes which are public, but show more to signed in
+/// accounts. Requests without an `Authorization` header get no session,
+/// invalid tokens are still rejected.
+pub fn optional_auth(
+    store: DynStore,
+) -> impl Filter<Extract = (Option<Session>,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("Authorization")
+        .and(warp::any().map(move || store.clone()))
+        .and_then(|token: Option<String>, store: DynStore| async move {
+            match token {
+                Some(token) => authenticate(token, store).await.map(Some),
+                None => Ok(None),
+            }
+        })
+}
 
-        future::ready(Ok(token))
+/// Like [`auth`], but additionally rejects sessions whose role is below `role`.
+pub fn require_role(
+    store: DynStore,
+    role: Role,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store).and_then(move |session: Session| {
+        if session.role >= role {
+            future::ready(Ok(session))
+        } else {
+            future::ready(Err(warp::reject::custom(
+                handle_errors::Error::Unauthorized,
+            )))
+        }
     })
 }
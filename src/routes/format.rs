@@ -0,0 +1,174 @@
+use std::io;
+
+use futures::{stream, Stream, StreamExt};
+use handle_errors::Error;
+use serde::Serialize;
+use warp::{
+    http::{header, Response},
+    hyper::{body::Bytes, Body},
+};
+
+use crate::types::{answer::Answer, question::Question};
+
+const JSON: &str = "application/json";
+const NDJSON: &str = "application/x-ndjson";
+const CSV: &str = "text/csv";
+
+/// Representation of a list response, picked from the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Json,
+    /// One JSON object per line, written as the rows arrive
+    Ndjson,
+    /// A header row and one row per item, for spreadsheets
+    Csv,
+}
+
+/// Picks the format with the highest quality the client accepts. JSON is
+/// used without an `Accept` header and for wildcards.
+pub fn negotiate(accept: Option<&str>) -> Result<ListFormat, Error> {
+    let accept = match accept.map(str::trim) {
+        None | Some("") => return Ok(ListFormat::Json),
+        Some(accept) => accept,
+    };
+
+    let mut ranges: Vec<(&str, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next().filter(|media_type| !media_type.is_empty())?;
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect();
+    // Stable, so equally preferred types keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges
+        .iter()
+        .find_map(
+            |(media_type, _)| match media_type.to_ascii_lowercase().as_str() {
+                JSON | "application/*" | "*/*" => Some(ListFormat::Json),
+                NDJSON | "application/ndjson" => Some(ListFormat::Ndjson),
+                CSV | "text/*" => Some(ListFormat::Csv),
+                _ => None,
+            },
+        )
+        .ok_or_else(|| Error::NotAcceptable(vec![JSON, NDJSON, CSV]))
+}
+
+/// A row of the CSV representation of [`Question`]
+#[derive(Serialize)]
+pub struct QuestionRow {
+    id: i32,
+    title: String,
+    content: String,
+    /// Separated by commas
+    tags: String,
+}
+
+impl QuestionRow {
+    pub const COLUMNS: &'static [&'static str] = &["id", "title", "content", "tags"];
+}
+
+impl From<Question> for QuestionRow {
+    fn from(question: Question) -> Self {
+        QuestionRow {
+            id: question.id.0,
+            title: question.title,
+            content: question.content,
+            tags: question.tags.unwrap_or_default().join(","),
+        }
+    }
+}
+
+/// A row of the CSV representation of [`Answer`]
+#[derive(Serialize)]
+pub struct AnswerRow {
+    id: i32,
+    question_id: i32,
+    content: String,
+}
+
+impl AnswerRow {
+    pub const COLUMNS: &'static [&'static str] = &["id", "question_id", "content"];
+}
+
+impl From<Answer> for AnswerRow {
+    fn from(answer: Answer) -> Self {
+        AnswerRow {
+            id: answer.id.0,
+            question_id: answer.question_id.0,
+            content: answer.content,
+        }
+    }
+}
+
+/// Streams `items` as NDJSON. An error ends the response early, which
+/// clients see as a truncated body, since the status was already sent.
+pub fn ndjson<T, S>(items: S) -> Response<Body>
+where
+    T: Serialize,
+    S: Stream<Item = Result<T, Error>> + Send + 'static,
+{
+    let lines = items.map(|item| {
+        let mut line = serde_json::to_vec(&item.map_err(stream_error)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        line.push(b'\n');
+        Ok::<_, io::Error>(Bytes::from(line))
+    });
+
+    streamed(NDJSON, Body::wrap_stream(lines))
+}
+
+/// Streams `rows` as CSV after a row with the names in `columns`.
+/// `filename` is offered to browsers saving the response.
+pub fn csv<R, S>(rows: S, columns: &'static [&'static str], filename: &str) -> Response<Body>
+where
+    R: Serialize,
+    S: Stream<Item = Result<R, Error>> + Send + 'static,
+{
+    let columns = stream::once(async move { csv_record(columns) });
+    let rows = rows.map(|row| csv_record(row.map_err(stream_error)?));
+
+    let mut response = streamed(
+        "text/csv; charset=utf-8",
+        Body::wrap_stream(columns.chain(rows)),
+    );
+    if let Ok(value) = format!("attachment; filename=\"{}\"", filename).parse() {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, value);
+    }
+    response
+}
+
+fn csv_record<R: Serialize>(record: R) -> Result<Bytes, io::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+    writer
+        .serialize(record)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// The store already logged the error, this only aborts the body
+fn stream_error(e: Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+fn streamed(content_type: &str, body: Body) -> Response<Body> {
+    let mut response = Response::new(body);
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    response
+}
@@ -0,0 +1,93 @@
+use std::{collections::BTreeMap, fmt::Display, future::Future, time::Duration};
+
+use crate::{
+    profanity::ContentPolicy,
+    shutdown::Draining,
+    store::DynStore,
+    types::health::{BuildInfo, CheckStatus, DependencyCheck, Health, Readiness, ReadinessStatus},
+};
+use warp::http::StatusCode;
+
+/// Probes are retried by the orchestrator, so a slow dependency counts as down
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn check<E: Display>(
+    critical: bool,
+    check: impl Future<Output = Result<(), E>>,
+) -> DependencyCheck {
+    let error = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some("check timed out".to_string()),
+    };
+
+    DependencyCheck {
+        status: match error {
+            None => CheckStatus::Up,
+            Some(_) => CheckStatus::Down,
+        },
+        critical,
+        error,
+    }
+}
+
+/// Liveness, answers as long as the server is running
+pub async fn get_health() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&Health { status: "ok" }))
+}
+
+/// Readiness, answers `503 Service Unavailable` while the database can't
+/// be used or the server is shutting down. The profanity backend is
+/// reported, but isn't critical since reads don't depend on it.
+pub async fn get_ready(
+    store: DynStore,
+    policy: ContentPolicy,
+    draining: Draining,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (database, migrations, profanity) = tokio::join!(
+        check(true, store.ping()),
+        check(true, async {
+            match store.pending_migrations().await {
+                Ok(0) => Ok(()),
+                Ok(pending) => Err(format!("{} migrations pending", pending)),
+                Err(e) => Err(e.to_string()),
+            }
+        }),
+        check(false, policy.ready()),
+    );
+
+    let readiness = Readiness::new(BTreeMap::from([
+        ("database", database),
+        ("migrations", migrations),
+        ("profanity", profanity),
+        (
+            "shutdown",
+            check(true, async {
+                if draining.is_draining() {
+                    Err("shutting down")
+                } else {
+                    Ok(())
+                }
+            })
+            .await,
+        ),
+    ]));
+    let status = match readiness.status {
+        ReadinessStatus::NotReady => StatusCode::SERVICE_UNAVAILABLE,
+        ReadinessStatus::Ready | ReadinessStatus::Degraded => StatusCode::OK,
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&readiness),
+        status,
+    ))
+}
+
+pub async fn get_version() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        build_id: env!("RUST_WEB_DEV_VERSION"),
+        commit: env!("RUST_WEB_DEV_COMMIT"),
+        platform: env!("RUST_WEB_DEV_PLATFORM"),
+    }))
+}
@@ -1,3 +1,330 @@
+use crate::{
+    profanity::ContentPolicy,
+    shutdown::Draining,
+    store::DynStore,
+    types::{account::Role, moderation::ContentStatus},
+};
+use serde::de::DeserializeOwned;
+use warp::{http::StatusCode, Filter};
+
+pub mod account;
 pub mod answer;
 pub mod authentication;
+pub mod format;
+pub mod health;
+pub mod moderation;
 pub mod question;
+pub mod request_id;
+#[cfg(test)]
+mod tests;
+
+/// Largest request body accepted, bigger ones are answered with
+/// `413 Payload Too Large`.
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// Assemble all API routes on top of the given storage backend and
+/// content moderation policy. Routes match the path before the method, so
+/// unknown paths are answered with `404 Not Found` rather than
+/// `405 Method Not Allowed`.
+pub fn router(
+    store: DynStore,
+    policy: ContentPolicy,
+    draining: Draining,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let auth = authentication::auth(store.clone());
+    let viewer = authentication::optional_auth(store.clone());
+    let moderator = authentication::require_role(store.clone(), Role::Moderator);
+    let admin = authentication::require_role(store.clone(), Role::Admin);
+    let store_filter = warp::any().map(move || store.clone());
+    let policy_filter = warp::any().map(move || policy.clone());
+    let draining_filter = warp::any().map(move || draining.clone());
+
+    let get_questions = warp::path("questions")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("accept"))
+        .and(viewer.clone())
+        .and(store_filter.clone())
+        .and_then(question::get_questions)
+        .with(warp::reply::with::header("vary", "accept"));
+
+    let search_questions = warp::path("questions")
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(viewer.clone())
+        .and(store_filter.clone())
+        .and_then(question::search_questions);
+
+    let get_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(viewer.clone())
+        .and(store_filter.clone())
+        .and_then(question::get_question);
+
+    let add_question = warp::path("questions")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(policy_filter.clone())
+        .and(json_body())
+        .and_then(question::add_question);
+
+    let update_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(policy_filter.clone())
+        .and(json_body())
+        .and_then(question::update_question);
+
+    let delete_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(question::delete_question);
+
+    let get_question_revisions = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("revisions"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(question::get_question_revisions);
+
+    let rollback_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("revisions"))
+        .and(warp::path::param::<i64>())
+        .and(warp::path("rollback"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(policy_filter.clone())
+        .and_then(question::rollback_question);
+
+    let get_answers = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::header::optional::<String>("accept"))
+        .and(viewer.clone())
+        .and(store_filter.clone())
+        .and_then(answer::get_answers)
+        .with(warp::reply::with::header("vary", "accept"));
+
+    let add_answer = warp::path("answers")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(policy_filter.clone())
+        .and(form_body())
+        .and_then(answer::add_answer);
+
+    let update_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(policy_filter.clone())
+        .and(json_body())
+        .and_then(answer::update_answer);
+
+    let delete_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(answer::delete_answer);
+
+    let flag_question = warp::path("questions")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("flags"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(moderation::flag_question);
+
+    let flag_answer = warp::path("answers")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("flags"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(moderation::flag_answer);
+
+    let moderation_queue = warp::path("moderation")
+        .and(warp::path("queue"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(moderator.clone())
+        .and(store_filter.clone())
+        .and_then(moderation::get_queue);
+
+    let decide_question = warp::path("moderation")
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("decisions"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(moderator.clone())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(moderation::decide_question);
+
+    let decide_answer = warp::path("moderation")
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("decisions"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(moderator)
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(moderation::decide_answer);
+
+    let registration = warp::path("registration")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(authentication::register);
+
+    let login = warp::path("login")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(authentication::login);
+
+    let refresh = warp::path("token")
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(authentication::refresh);
+
+    let logout = warp::path("logout")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth.clone())
+        .and(store_filter.clone())
+        .and_then(authentication::logout);
+
+    let get_accounts = warp::path("accounts")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and_then(account::get_accounts);
+
+    let set_account_role = warp::path("accounts")
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and(json_body())
+        .and_then(account::set_account_role);
+
+    let delete_account = warp::path("accounts")
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and_then(account::delete_account);
+
+    let health = warp::path("health")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and_then(health::get_health);
+
+    let ready = warp::path("ready")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter)
+        .and(policy_filter)
+        .and(draining_filter)
+        .and_then(health::get_ready);
+
+    let version = warp::path("version")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and_then(health::get_version);
+
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and_then(crate::metrics::get_metrics);
+
+    get_questions
+        .or(search_questions)
+        .or(get_question)
+        .or(add_question)
+        .or(update_question)
+        .or(delete_question)
+        .or(get_question_revisions)
+        .or(rollback_question)
+        .or(get_answers)
+        .or(add_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(flag_question)
+        .or(flag_answer)
+        .or(moderation_queue)
+        .or(decide_question)
+        .or(decide_answer)
+        .or(registration)
+        .or(login)
+        .or(refresh)
+        .or(logout)
+        .or(get_accounts)
+        .or(set_account_role)
+        .or(delete_account)
+        .or(health)
+        .or(ready)
+        .or(version)
+        .or(metrics)
+}
+
+fn json_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(MAX_BODY_BYTES).and(warp::body::json())
+}
+
+fn form_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(MAX_BODY_BYTES).and(warp::body::form())
+}
+
+/// Content which isn't visible to others, like posts held for review, is
+/// answered with `202 Accepted`.
+fn status_code(status: ContentStatus) -> StatusCode {
+    match status {
+        ContentStatus::Published => StatusCode::OK,
+        ContentStatus::Pending | ContentStatus::Hidden => StatusCode::ACCEPTED,
+    }
+}
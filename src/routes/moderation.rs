@@ -0,0 +1,210 @@
+use crate::{
+    store::DynStore,
+    types::{
+        account::{Session, Viewer},
+        answer::Answer,
+        moderation::{
+            ContentKind, ContentRef, ContentStatus, DecisionRequest, ModerationAction, NewFlag,
+        },
+        question::Question,
+    },
+};
+use handle_errors::Error;
+use tracing::{event, instrument, Level};
+
+pub async fn flag_question(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    flag: NewFlag,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    add_flag(
+        ContentRef {
+            kind: ContentKind::Question,
+            id,
+        },
+        session,
+        store,
+        flag,
+    )
+    .await
+}
+
+pub async fn flag_answer(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    flag: NewFlag,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    add_flag(
+        ContentRef {
+            kind: ContentKind::Answer,
+            id,
+        },
+        session,
+        store,
+        flag,
+    )
+    .await
+}
+
+async fn add_flag(
+    target: ContentRef,
+    session: Session,
+    store: DynStore,
+    flag: NewFlag,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let reason = flag.reason.trim();
+    if reason.is_empty() {
+        return Err(warp::reject::custom(Error::InvalidParameter(
+            "reason".to_string(),
+            "must not be empty".to_string(),
+        )));
+    }
+
+    // Content the flagger can't see is reported as missing, so flags can't
+    // be used to find out which held or hidden posts exist
+    let viewer = Viewer::from(Some(session.clone()));
+    match target.kind {
+        ContentKind::Question => {
+            store.get_question(target.id, &viewer).await?;
+        }
+        ContentKind::Answer => {
+            store.get_answer(target.id, &viewer).await?;
+        }
+    }
+
+    match store
+        .add_flag(target, session.account_id, reason.to_string())
+        .await
+    {
+        Ok(flag) => Ok(warp::reply::json(&flag)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[instrument]
+pub async fn get_queue(
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_moderation_queue().await {
+        Ok(queue) => Ok(warp::reply::json(&queue)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn decide_question(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    request: DecisionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    decide(
+        ContentRef {
+            kind: ContentKind::Question,
+            id,
+        },
+        session,
+        store,
+        request,
+    )
+    .await
+}
+
+pub async fn decide_answer(
+    id: i32,
+    session: Session,
+    store: DynStore,
+    request: DecisionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    decide(
+        ContentRef {
+            kind: ContentKind::Answer,
+            id,
+        },
+        session,
+        store,
+        request,
+    )
+    .await
+}
+
+/// Carry out a moderator's decision and record it. Edits by moderators
+/// are the result of a review, so they aren't run through the moderation
+/// policy again. They publish held content, hidden content stays hidden.
+#[instrument]
+async fn decide(
+    target: ContentRef,
+    session: Session,
+    store: DynStore,
+    request: DecisionRequest,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    event!(
+        target: "test_warp",
+        Level::INFO,
+        "account {} decides to {} {} {}",
+        session.account_id.0,
+        request.action,
+        target.kind.as_str(),
+        target.id
+    );
+
+    let status = match store.get_content_status(target).await? {
+        ContentStatus::Hidden => ContentStatus::Hidden,
+        _ => ContentStatus::Published,
+    };
+
+    match request.action {
+        ModerationAction::Approve => {
+            store
+                .set_content_status(target, ContentStatus::Published)
+                .await?;
+        }
+        ModerationAction::Hide => {
+            store
+                .set_content_status(target, ContentStatus::Hidden)
+                .await?;
+        }
+        ModerationAction::Edit => {
+            let viewer = Viewer::from(Some(session.clone()));
+            match target.kind {
+                ContentKind::Question => {
+                    let question = store.get_question(target.id, &viewer).await?;
+                    let question = Question {
+                        id: question.id,
+                        title: request.title.unwrap_or(question.title),
+                        content: request.content.unwrap_or(question.content),
+                        tags: request.tags.or(question.tags),
+                    };
+                    store
+                        .update_question(question, status, &session.account_id)
+                        .await?;
+                }
+                ContentKind::Answer => {
+                    let answer = store.get_answer(target.id, &viewer).await?;
+                    let answer = Answer {
+                        id: answer.id,
+                        content: request.content.unwrap_or(answer.content),
+                        question_id: answer.question_id,
+                    };
+                    store.update_answer(answer, status).await?;
+                }
+            }
+        }
+        ModerationAction::Delete => {
+            match target.kind {
+                ContentKind::Question => store.delete_question(target.id).await?,
+                ContentKind::Answer => store.delete_answer(target.id).await?,
+            };
+        }
+    }
+
+    match store
+        .add_decision(target, session.account_id, request.action, request.note)
+        .await
+    {
+        Ok(decision) => Ok(warp::reply::json(&decision)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
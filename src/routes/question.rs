@@ -1,65 +1,225 @@
 use crate::{
-    profanity::check_profanity,
-    store::Store,
+    profanity::ContentPolicy,
+    routes::{
+        format::{self, ListFormat, QuestionRow},
+        status_code,
+    },
+    store::DynStore,
     types::{
-        account::Session,
-        pagination::{extract_pagination, Pagination},
-        question::{NewQuestion, Question},
+        account::{Session, Viewer},
+        filter::extract_filter,
+        moderation::{ContentKind, ContentRef, ContentStatus},
+        pagination::{extract_cursor_pagination, extract_pagination, Page, Pagination},
+        question::{NewQuestion, Question, QuestionId, QuestionQuery, QuestionWithAnswers},
+        revision::with_diffs,
     },
 };
+use futures::StreamExt;
+use handle_errors::Error;
 use std::collections::HashMap;
 use tracing::{event, instrument, Level};
+use warp::{
+    http::{header, HeaderValue},
+    Reply,
+};
 
 #[instrument]
 pub async fn get_questions(
     params: HashMap<String, String>,
-    store: Store,
+    accept: Option<String>,
+    session: Option<Session>,
+    store: DynStore,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     event!(target: "test_warp", Level::INFO, "querying questions");
-    let mut pagination = Pagination::default();
+    let list_format = format::negotiate(accept.as_deref())?;
+    let filter = extract_filter(&params)?;
+    let viewer = Viewer::from(session);
+
+    // The streamed formats are meant for exports, so they return the whole
+    // list unless `limit` or `offset` is given
+    if list_format != ListFormat::Json {
+        if params.contains_key("cursor") {
+            return Err(warp::reject::custom(Error::InvalidParameter(
+                "cursor".to_string(),
+                "is only supported for application/json".to_string(),
+            )));
+        }
+        let pagination = extract_pagination(params)?;
+
+        let questions = store.stream_questions(filter, viewer, pagination.limit, pagination.offset);
+        return Ok(match list_format {
+            ListFormat::Csv => format::csv(
+                questions.map(|question| question.map(QuestionRow::from)),
+                QuestionRow::COLUMNS,
+                "questions.csv",
+            ),
+            _ => format::ndjson(questions),
+        }
+        .into_response());
+    }
 
-    if !params.is_empty() {
+    // Offset pagination is kept for existing clients and returns a bare list
+    if params.contains_key("offset") {
         event!(Level::INFO, pagination = true);
-        pagination = extract_pagination(params)?;
+        let pagination = extract_pagination(params)?;
+
+        let res = match store
+            .get_questions(filter, &viewer, pagination.limit, pagination.offset)
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+
+        return Ok(warp::reply::json(&res).into_response());
     }
 
-    let res = match store
-        .get_questions(pagination.limit, pagination.offset)
+    let (cursor, limit) = extract_cursor_pagination(&params, filter.sort)?;
+    let page = match store
+        .get_questions_page(filter, &viewer, cursor, limit)
         .await
     {
-        Ok(res) => res,
+        Ok(page) => page,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    Ok(warp::reply::json(&res))
+    let mut response = warp::reply::json(&page).into_response();
+    response
+        .headers_mut()
+        .insert(header::LINK, link_header(&params, &page));
+
+    Ok(response)
+}
+
+/// RFC 8288 `Link` header pointing to the first and neighbouring pages of
+/// the question list, keeping all other query parameters.
+fn link_header<T>(params: &HashMap<String, String>, page: &Page<T>) -> HeaderValue {
+    let mut query: Vec<(&str, &str)> = params
+        .iter()
+        .filter(|(name, _)| name.as_str() != "cursor")
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    query.sort_unstable();
+
+    let link = |cursor: Option<&str>, rel: &str| {
+        let mut query = query.clone();
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor));
+        }
+        format!(
+            "</questions?{}>; rel=\"{}\"",
+            serde_urlencoded::to_string(query).expect("query parameters are always encodable"),
+            rel
+        )
+    };
+
+    let mut links = vec![link(None, "first")];
+    if let Some(next) = &page.next {
+        links.push(link(Some(next), "next"));
+    }
+    if let Some(prev) = &page.prev {
+        links.push(link(Some(prev), "prev"));
+    }
+
+    HeaderValue::from_str(&links.join(", ")).expect("link header is always valid ASCII")
 }
 
 #[instrument]
-pub async fn add_question(
-    store: Store,
-    question: NewQuestion,
+pub async fn search_questions(
+    params: HashMap<String, String>,
+    session: Option<Session>,
+    store: DynStore,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let title = check_profanity(question.title);
-    let content = check_profanity(question.content);
+    event!(target: "test_warp", Level::INFO, "searching questions");
+    let query = match params.get("q") {
+        Some(query) if !query.trim().is_empty() => query.clone(),
+        _ => {
+            return Err(warp::reject::custom(
+                handle_errors::Error::MissingParameters,
+            ))
+        }
+    };
 
-    let (title, content) = tokio::join!(title, content);
+    let mut pagination = Pagination::default();
 
-    if let Err(e) = title {
-        return Err(warp::reject::custom(e));
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
     }
 
-    if let Err(e) = content {
-        return Err(warp::reject::custom(e));
+    match store
+        .search_questions(
+            &query,
+            &Viewer::from(session),
+            pagination.limit,
+            pagination.offset,
+        )
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
     }
+}
+
+#[instrument]
+pub async fn get_question(
+    id: i32,
+    params: QuestionQuery,
+    session: Option<Session>,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "test_warp", Level::INFO, "querying question {}", id);
+    let viewer = Viewer::from(session);
+    let question = match store.get_question(id, &viewer).await {
+        Ok(question) => question,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let answers = if params.answers {
+        match store.get_answers(id, &viewer).await {
+            Ok(answers) => Some(answers),
+            Err(e) => return Err(warp::reject::custom(e)),
+        }
+    } else {
+        None
+    };
+
+    Ok(warp::reply::json(&QuestionWithAnswers {
+        question,
+        answers,
+    }))
+}
+
+#[instrument]
+pub async fn add_question(
+    session: Session,
+    store: DynStore,
+    policy: ContentPolicy,
+    question: NewQuestion,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let moderated = match policy
+        .moderate_question(question.title, question.content)
+        .await
+    {
+        Ok(moderated) => moderated,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let (title, content) = moderated.content;
 
     let question = NewQuestion {
-        title: title.unwrap(),
-        content: content.unwrap(),
+        title,
+        content,
         tags: question.tags,
     };
 
-    match store.add_question(question).await {
-        Ok(question) => Ok(warp::reply::json(&question)),
+    match store
+        .add_question(question, session.account_id, moderated.status)
+        .await
+    {
+        Ok(question) => Ok(warp::reply::with_status(
+            warp::reply::json(&question),
+            status_code(moderated.status),
+        )),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
@@ -67,43 +227,137 @@ pub async fn add_question(
 pub async fn update_question(
     id: i32,
     session: Session,
-    store: Store,
+    store: DynStore,
+    policy: ContentPolicy,
     question: Question,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let account_id = session.account_id;
-    if store.is_question_owner(id, &account_id).await? {
-        let title = check_profanity(question.title);
-        let content = check_profanity(question.content);
+    if session.can_moderate() || store.is_question_owner(id, &session.account_id).await? {
+        let question = Question {
+            id: QuestionId(id),
+            ..question
+        };
+        save_edit(question, session, store, policy).await
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
 
-        let (title, content) = tokio::join!(title, content);
+/// Runs an edit through the moderation policy and stores it
+async fn save_edit(
+    question: Question,
+    session: Session,
+    store: DynStore,
+    policy: ContentPolicy,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    let moderated = match policy
+        .moderate_question(question.title, question.content)
+        .await
+    {
+        Ok(moderated) => moderated,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let (title, content) = moderated.content;
 
-        if title.is_err() {
-            return Err(warp::reject::custom(title.unwrap_err()));
-        }
+    // Content taken down by a moderator stays hidden when it's edited
+    let status = match store
+        .get_content_status(ContentRef {
+            kind: ContentKind::Question,
+            id: question.id.0,
+        })
+        .await?
+    {
+        ContentStatus::Hidden => ContentStatus::Hidden,
+        _ => moderated.status,
+    };
 
-        if content.is_err() {
-            return Err(warp::reject::custom(content.unwrap_err()));
-        }
+    let question = Question {
+        title,
+        content,
+        ..question
+    };
 
-        let question = Question {
-            id: question.id,
-            title: title.unwrap(),
-            content: content.unwrap(),
-            tags: question.tags,
-        };
+    match store
+        .update_question(question, status, &session.account_id)
+        .await
+    {
+        Ok(question) => Ok(warp::reply::with_status(
+            warp::reply::json(&question),
+            status_code(status),
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
 
-        match store.update_question(question, account_id).await {
-            Ok(question) => Ok(warp::reply::json(&question)),
+/// Every earlier version of a question with what the edit replacing it
+/// changed. Revisions can hold text the moderation policy or a moderator
+/// took out, so they're only shown to the author and moderators.
+#[instrument]
+pub async fn get_question_revisions(
+    id: i32,
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "test_warp", Level::INFO, "querying revisions of question {}", id);
+    let viewer = Viewer::from(Some(session.clone()));
+    let question = match store.get_question(id, &viewer).await {
+        Ok(question) => question,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    if !session.can_moderate() && !store.is_question_owner(id, &session.account_id).await? {
+        return Err(warp::reject::custom(Error::Unauthorized));
+    }
+
+    match store.get_question_revisions(id).await {
+        Ok(revisions) => Ok(warp::reply::json(&with_diffs(revisions, &question))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Restores the title, content and tags a question had before the given
+/// revision. The rollback is an edit itself, so it can be undone the same
+/// way and goes through the moderation policy like any other edit.
+pub async fn rollback_question(
+    id: i32,
+    revision: i64,
+    session: Session,
+    store: DynStore,
+    policy: ContentPolicy,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !session.can_moderate() && !store.is_question_owner(id, &session.account_id).await? {
+        return Err(warp::reject::custom(Error::Unauthorized));
+    }
+
+    match store
+        .get_question_revisions(id)
+        .await?
+        .into_iter()
+        .find(|entry| entry.revision == revision)
+    {
+        Some(entry) => save_edit(entry.question(), session, store, policy).await,
+        None => Err(warp::reject::custom(Error::NotFound)),
+    }
+}
+
+pub async fn delete_question(
+    id: i32,
+    session: Session,
+    store: DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Missing content is reported as such rather than as not being owned
+    store
+        .get_content_status(ContentRef {
+            kind: ContentKind::Question,
+            id,
+        })
+        .await?;
+
+    if session.can_moderate() || store.is_question_owner(id, &session.account_id).await? {
+        match store.delete_question(id).await {
+            Ok(true) => Ok(warp::reply::json(&id)),
+            Ok(false) => Err(warp::reject::custom(handle_errors::Error::NotFound)),
             Err(e) => Err(warp::reject::custom(e)),
         }
     } else {
         Err(warp::reject::custom(handle_errors::Error::Unauthorized))
     }
 }
-
-pub async fn delete_question(id: i32, store: Store) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.delete_question(id).await {
-        Ok(_) => Ok(warp::reply::json(&id)),
-        Err(e) => Err(warp::reject::custom(e)),
-    }
-}
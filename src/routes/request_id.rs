@@ -0,0 +1,139 @@
+use handle_errors::return_error;
+use tracing::Span;
+use uuid::Uuid;
+use warp::{
+    http::{HeaderMap, HeaderValue},
+    reply::Response,
+    trace::Info,
+    Filter, Rejection, Reply,
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Takes the client's request ID if it has one, otherwise generates one.
+/// IDs with unexpected characters are replaced, so they can't be used to
+/// forge log lines.
+fn request_id(headers: HeaderMap) -> String {
+    let id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match id {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= 128
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c)) =>
+        {
+            id.to_string()
+        }
+        _ => Uuid::new_v4().to_string(),
+    }
+}
+
+/// Span around each request, like `warp::trace::request()` plus the
+/// request ID filled in by [`with_request_id`].
+pub fn request_span(info: Info) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %info.method(),
+        path = %info.path(),
+        version = ?info.version(),
+        remote.addr = %info
+            .remote_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default(),
+        request_id = tracing::field::Empty,
+    )
+}
+
+/// Serves `routes` with a request ID, which is recorded on the current
+/// `request` span, echoed in the `X-Request-Id` response header and part
+/// of every error body. Rejections are turned into error responses here,
+/// so this filter never rejects.
+pub fn with_request_id<F, T>(
+    routes: F,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (T,), Error = Rejection> + Clone + Send + Sync + 'static,
+    T: Reply,
+{
+    warp::header::headers_cloned()
+        .map(|headers: HeaderMap| {
+            let id = request_id(headers);
+            Span::current().record("request_id", &tracing::field::display(&id));
+            id
+        })
+        .and(
+            routes.map(|reply: T| Ok(reply.into_response())).or_else(
+                |rejection: Rejection| async move { Ok::<_, Rejection>((Err(rejection),)) },
+            ),
+        )
+        .map(|id: String, result: Result<Response, Rejection>| {
+            let mut response = match result {
+                Ok(response) => response,
+                Err(rejection) => return_error(&rejection, &id),
+            };
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                response.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            response
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::test::request;
+
+    fn routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+        with_request_id(warp::path("ok").map(warp::reply))
+    }
+
+    async fn response_id(request: warp::test::RequestBuilder) -> String {
+        let res = request.reply(&routes()).await;
+        res.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn client_request_ids_are_kept() {
+        let id = response_id(
+            request()
+                .path("/ok")
+                .header(REQUEST_ID_HEADER, "abc-123.x:y_z"),
+        )
+        .await;
+        assert_eq!(id, "abc-123.x:y_z");
+    }
+
+    #[tokio::test]
+    async fn missing_request_ids_are_generated() {
+        let id = response_id(request().path("/ok")).await;
+        assert!(Uuid::parse_str(&id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn unexpected_request_ids_are_replaced() {
+        for forged in ["", "a b", "a\"b", &"a".repeat(129)] {
+            let id = response_id(request().path("/ok").header(REQUEST_ID_HEADER, forged)).await;
+            assert!(Uuid::parse_str(&id).is_ok(), "{:?} was kept", forged);
+        }
+    }
+
+    #[tokio::test]
+    async fn error_bodies_carry_the_request_id() {
+        let res = request()
+            .path("/missing")
+            .header(REQUEST_ID_HEADER, "abc")
+            .reply(&routes())
+            .await;
+        let problem: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "abc");
+        assert_eq!(problem["request_id"], "abc");
+    }
+}
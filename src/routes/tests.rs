@@ -0,0 +1,1052 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use warp::{
+    filters::BoxedFilter,
+    http::StatusCode,
+    reply::Response,
+    test::{request, RequestBuilder},
+    Filter,
+};
+
+use super::{authentication::set_paseto_key, request_id::with_request_id, router};
+use crate::{
+    profanity::{
+        fake_bad_words, ApiConfig, ApiLayerChecker, ContentPolicy, ModerationPolicy,
+        WordlistChecker,
+    },
+    shutdown::Draining,
+    store::{memory::InMemoryStore, DynStore},
+    types::account::Role,
+};
+
+struct App {
+    store: DynStore,
+    draining: Draining,
+    routes: BoxedFilter<(Response,)>,
+}
+
+impl App {
+    /// All routes over an empty in-memory store. Questions containing
+    /// "shoot" are held for review, answers containing it are censored.
+    fn new() -> Self {
+        App::with_policy(ContentPolicy::new(
+            Arc::new(WordlistChecker::new(["shoot"])),
+            ModerationPolicy::Review,
+            ModerationPolicy::Censor,
+        ))
+    }
+
+    fn with_policy(policy: ContentPolicy) -> Self {
+        set_paseto_key("RANDOM WORDS WINTER MACINTOSH PC".to_string());
+        let store: DynStore = Arc::new(InMemoryStore::new());
+        let draining = Draining::default();
+        let routes = with_request_id(router(store.clone(), policy, draining.clone())).boxed();
+
+        App {
+            store,
+            draining,
+            routes,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> (StatusCode, Value) {
+        let res = request.reply(&self.routes).await;
+        let body = serde_json::from_slice(res.body()).unwrap_or(Value::Null);
+
+        (res.status(), body)
+    }
+
+    /// Registers an account and returns the token pair of its first login
+    async fn sign_up(&self, email: &str) -> Value {
+        let account = json!({ "email": email, "password": "secret" });
+        let (status, _) = self
+            .send(
+                request()
+                    .method("POST")
+                    .path("/registration")
+                    .json(&account),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        self.login(email).await
+    }
+
+    async fn login(&self, email: &str) -> Value {
+        let account = json!({ "email": email, "password": "secret" });
+        let (status, tokens) = self
+            .send(request().method("POST").path("/login").json(&account))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        tokens
+    }
+
+    /// Registers a moderator and returns its access token
+    async fn moderator(&self, email: &str) -> String {
+        self.sign_up(email).await;
+        let account = self.store.get_account(email.to_string()).await.unwrap();
+        self.store
+            .set_account_role(account.id.unwrap().0, Role::Moderator)
+            .await
+            .unwrap();
+
+        // The role is part of the token, so it only applies to new logins
+        access_token(&self.login(email).await)
+    }
+
+    async fn add_question(&self, token: &str, content: &str) -> (StatusCode, Value) {
+        self.post_question(
+            token,
+            json!({ "title": "Title", "content": content, "tags": null }),
+        )
+        .await
+    }
+
+    async fn post_question(&self, token: &str, question: Value) -> (StatusCode, Value) {
+        self.send(
+            request()
+                .method("POST")
+                .path("/questions")
+                .header("Authorization", token)
+                .json(&question),
+        )
+        .await
+    }
+
+    async fn edit_question(&self, token: &str, id: i64, content: &str) -> (StatusCode, Value) {
+        self.send(
+            request()
+                .method("PUT")
+                .path(&format!("/questions/{}", id))
+                .header("Authorization", token)
+                .json(&json!({ "id": id, "title": "Title", "content": content, "tags": null })),
+        )
+        .await
+    }
+
+    /// Ids of the questions on the first page of `/questions?{query}`
+    async fn question_ids(&self, query: &str) -> Vec<Value> {
+        let (status, page) = self
+            .send(request().path(&format!("/questions?{}", query)))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|question| question["id"].clone())
+            .collect()
+    }
+
+    async fn add_answer(&self, token: &str, question_id: i64) -> Value {
+        let (status, answer) = self
+            .send(
+                request()
+                    .method("POST")
+                    .path("/answers")
+                    .header("Authorization", token)
+                    .header("content-type", "application/x-www-form-urlencoded")
+                    .body(format!("content=An+answer&question_id={}", question_id)),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        answer
+    }
+}
+
+fn access_token(tokens: &Value) -> String {
+    tokens["access_token"].as_str().unwrap().to_string()
+}
+
+fn code(problem: &Value) -> &str {
+    problem["code"].as_str().unwrap()
+}
+
+#[tokio::test]
+async fn adding_a_question_requires_a_login() {
+    let app = App::new();
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path("/questions")
+                .json(&json!({ "title": "Title", "content": "Content", "tags": null })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, problem) = app.add_question("not a token", "Content").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(code(&problem), "invalid_token");
+}
+
+#[tokio::test]
+async fn login_with_a_wrong_password_is_refused() {
+    let app = App::new();
+    app.sign_up("alice@example.com").await;
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("POST")
+                .path("/login")
+                .json(&json!({ "email": "alice@example.com", "password": "wrong" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(code(&problem), "wrong_credentials");
+}
+
+#[tokio::test]
+async fn only_the_author_can_change_a_question() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+
+    let (status, question) = app.add_question(&alice, "Content").await;
+    assert_eq!(status, StatusCode::OK);
+    let id = question["id"].as_i64().unwrap();
+    let edit = json!({ "id": id, "title": "Edited", "content": "Edited", "tags": null });
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("PUT")
+                .path(&format!("/questions/{}", id))
+                .header("Authorization", &bob)
+                .json(&edit),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(code(&problem), "forbidden");
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("DELETE")
+                .path(&format!("/questions/{}", id))
+                .header("Authorization", &bob),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(code(&problem), "forbidden");
+
+    let (status, question) = app
+        .send(
+            request()
+                .method("PUT")
+                .path(&format!("/questions/{}", id))
+                .header("Authorization", &alice)
+                .json(&edit),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(question["title"], "Edited");
+}
+
+#[tokio::test]
+async fn changing_a_missing_question_is_not_found() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("DELETE")
+                .path("/questions/42")
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(code(&problem), "not_found");
+}
+
+#[tokio::test]
+async fn deleting_a_question_deletes_its_answers() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+
+    let (_, question) = app.add_question(&alice, "Content").await;
+    let question_id = question["id"].as_i64().unwrap();
+    let answer = app.add_answer(&bob, question_id).await;
+    let answer_id = answer["id"].as_i64().unwrap();
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("DELETE")
+                .path(&format!("/questions/{}", question_id))
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("DELETE")
+                .path(&format!("/answers/{}", answer_id))
+                .header("Authorization", &bob),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn updating_an_answer_only_takes_its_content() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let (_, question) = app.add_question(&alice, "Content").await;
+    let question_id = question["id"].as_i64().unwrap();
+    let answer = app.add_answer(&alice, question_id).await;
+
+    let (status, answer) = app
+        .send(
+            request()
+                .method("PUT")
+                .path(&format!("/answers/{}", answer["id"]))
+                .header("Authorization", &alice)
+                .json(&json!({ "content": "shoot, edited" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(answer["content"], "*****, edited");
+    assert_eq!(answer["question_id"], question_id);
+}
+
+#[tokio::test]
+async fn refresh_tokens_can_only_be_used_once() {
+    let app = App::new();
+    let tokens = app.sign_up("alice@example.com").await;
+    let refresh = json!({ "refresh_token": tokens["refresh_token"] });
+
+    let (status, renewed) = app
+        .send(
+            request()
+                .method("POST")
+                .path("/token/refresh")
+                .json(&refresh),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(renewed["access_token"].is_string());
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("POST")
+                .path("/token/refresh")
+                .json(&refresh),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(code(&problem), "token_revoked");
+}
+
+#[tokio::test]
+async fn logout_revokes_the_access_token() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path("/logout")
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, problem) = app.add_question(&alice, "Content").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(code(&problem), "token_revoked");
+}
+
+#[tokio::test]
+async fn the_moderation_queue_is_for_moderators_only() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (status, problem) = app
+        .send(
+            request()
+                .path("/moderation/queue")
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(code(&problem), "forbidden");
+}
+
+#[tokio::test]
+async fn held_questions_are_published_once_approved() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let moderator = app.moderator("mod@example.com").await;
+
+    let (status, question) = app.add_question(&alice, "Oh shoot").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let id = question["id"].as_i64().unwrap();
+    let path = format!("/questions/{}", id);
+
+    let (status, _) = app.send(request().path(&path)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app
+        .send(request().path(&path).header("Authorization", &alice))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, queue) = app
+        .send(
+            request()
+                .path("/moderation/queue")
+                .header("Authorization", &moderator),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(queue[0]["kind"], "question");
+    assert_eq!(queue[0]["id"], id);
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/moderation/questions/{}/decisions", id))
+                .header("Authorization", &moderator)
+                .json(&json!({ "action": "approve" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app.send(request().path(&path)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn flagged_questions_are_queued_for_moderators() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+    let moderator = app.moderator("mod@example.com").await;
+
+    let (_, question) = app.add_question(&alice, "Content").await;
+    let id = question["id"].as_i64().unwrap();
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/questions/{}/flags", id))
+                .header("Authorization", &bob)
+                .json(&json!({ "reason": "spam" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, queue) = app
+        .send(
+            request()
+                .path("/moderation/queue")
+                .header("Authorization", &moderator),
+        )
+        .await;
+    assert_eq!(queue[0]["id"], id);
+    assert_eq!(queue[0]["flags"][0]["reason"], "spam");
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/moderation/questions/{}/decisions", id))
+                .header("Authorization", &moderator)
+                .json(&json!({ "action": "hide", "note": "spam" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app
+        .send(request().path(&format!("/questions/{}", id)))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn content_hidden_from_an_account_cant_be_flagged_by_it() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+
+    let (status, question) = app.add_question(&alice, "Oh shoot").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let (status, problem) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/questions/{}/flags", question["id"]))
+                .header("Authorization", &bob)
+                .json(&json!({ "reason": "spam" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(code(&problem), "not_found");
+}
+
+#[tokio::test]
+async fn hidden_questions_stay_hidden_when_a_moderator_edits_them() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let moderator = app.moderator("mod@example.com").await;
+
+    let (_, question) = app.add_question(&alice, "Content").await;
+    let path = format!("/moderation/questions/{}/decisions", question["id"]);
+
+    for decision in [
+        json!({ "action": "hide" }),
+        json!({ "action": "edit", "title": "Fixed typo" }),
+    ] {
+        let (status, _) = app
+            .send(
+                request()
+                    .method("POST")
+                    .path(&path)
+                    .header("Authorization", &moderator)
+                    .json(&decision),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, _) = app
+        .send(request().path(&format!("/questions/{}", question["id"])))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn revisions_show_what_each_edit_changed() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (_, question) = app.add_question(&alice, "First").await;
+    let id = question["id"].as_i64().unwrap();
+    app.edit_question(&alice, id, "Second").await;
+    app.edit_question(&alice, id, "Third").await;
+
+    let (status, revisions) = app
+        .send(
+            request()
+                .path(&format!("/questions/{}/revisions", id))
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(revisions.as_array().unwrap().len(), 2);
+    assert_eq!(revisions[0]["revision"], 1);
+    assert_eq!(revisions[0]["content"], "First");
+    assert_eq!(
+        revisions[0]["diff"]["content"],
+        "--- revision 1\n+++ revision 2\n@@ -1 +1 @@\n-First\n+Second\n"
+    );
+    assert_eq!(
+        revisions[1]["diff"]["content"],
+        "--- revision 2\n+++ current\n@@ -1 +1 @@\n-Second\n+Third\n"
+    );
+    assert!(revisions[1]["diff"].get("title").is_none());
+}
+
+#[tokio::test]
+async fn revisions_are_only_shown_to_the_author_and_moderators() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+    let moderator = app.moderator("mod@example.com").await;
+
+    let (_, question) = app.add_question(&alice, "Oh shoot").await;
+    let id = question["id"].as_i64().unwrap();
+    app.edit_question(&alice, id, "Clean").await;
+    let path = format!("/questions/{}/revisions", id);
+
+    let (status, _) = app.send(request().path(&path)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, problem) = app
+        .send(request().path(&path).header("Authorization", &bob))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(code(&problem), "forbidden");
+    let (status, _) = app
+        .send(request().path(&path).header("Authorization", &moderator))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rollbacks_restore_a_revision_as_a_new_edit() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+
+    let (_, question) = app.add_question(&alice, "First").await;
+    let id = question["id"].as_i64().unwrap();
+    app.edit_question(&alice, id, "Second").await;
+    let path = format!("/questions/{}/revisions/1/rollback", id);
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &bob),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, question) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(question["content"], "First");
+
+    let (_, revisions) = app
+        .send(
+            request()
+                .path(&format!("/questions/{}/revisions", id))
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(revisions[1]["content"], "Second");
+
+    let (status, _) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/questions/{}/revisions/9/rollback", id))
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rollbacks_go_through_the_moderation_policy() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (status, question) = app.add_question(&alice, "Oh shoot").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let id = question["id"].as_i64().unwrap();
+    let (status, _) = app.edit_question(&alice, id, "Clean").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, question) = app
+        .send(
+            request()
+                .method("POST")
+                .path(&format!("/questions/{}/revisions/1/rollback", id))
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(question["content"], "Oh shoot");
+
+    let (status, _) = app
+        .send(request().path(&format!("/questions/{}", id)))
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn questions_are_checked_by_the_bad_words_api() {
+    let routes = fake_bad_words::routes("test-key".to_string(), fake_bad_words::wordlist());
+    let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let config = ApiConfig {
+        url: format!("http://{}/bad_words", addr),
+        max_retries: 0,
+        ..ApiConfig::default()
+    };
+    let checker = ApiLayerChecker::new(&config, "test-key".to_string()).unwrap();
+    let app = App::with_policy(ContentPolicy::new(
+        Arc::new(checker),
+        ModerationPolicy::Review,
+        ModerationPolicy::Review,
+    ));
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (status, question) = app.add_question(&alice, "you arse").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(question["content"], "you arse");
+
+    let (status, problem) = app.add_question(&alice, "__client_error__").await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(code(&problem), "upstream_error");
+}
+
+#[tokio::test]
+async fn search_ranks_title_matches_first_and_highlights_terms() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+
+    let (_, in_content) = app
+        .post_question(
+            &alice,
+            json!({ "title": "Structs", "content": "Which lifetimes do I need?", "tags": null }),
+        )
+        .await;
+    let (_, in_title) = app
+        .post_question(
+            &alice,
+            json!({ "title": "Lifetimes explained", "content": "Please", "tags": null }),
+        )
+        .await;
+    let (_, in_answer) = app
+        .post_question(
+            &alice,
+            json!({ "title": "Borrowing", "content": "Help", "tags": null }),
+        )
+        .await;
+    app.send(
+        request()
+            .method("POST")
+            .path("/answers")
+            .header("Authorization", &alice)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(format!(
+                "content=Read+up+on+lifetimes&question_id={}",
+                in_answer["id"]
+            )),
+    )
+    .await;
+    app.post_question(
+        &alice,
+        json!({ "title": "Unrelated", "content": "Nothing to see", "tags": null }),
+    )
+    .await;
+
+    let (status, found) = app
+        .send(request().path("/questions/search?q=Lifetimes"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["total"], 3);
+    assert_eq!(found["results"][0]["id"], in_title["id"]);
+    assert_eq!(
+        found["results"][0]["title_highlight"],
+        "<b>Lifetimes</b> explained"
+    );
+    let ids: Vec<&Value> = found["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| &result["id"])
+        .collect();
+    assert!(ids.contains(&&in_content["id"]));
+    assert!(ids.contains(&&in_answer["id"]));
+}
+
+#[tokio::test]
+async fn search_counts_all_matches_beyond_the_page() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    for _ in 0..3 {
+        app.add_question(&alice, "About traits").await;
+    }
+
+    let (_, page) = app
+        .send(request().path("/questions/search?q=traits&limit=2&offset=1"))
+        .await;
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["results"].as_array().unwrap().len(), 2);
+
+    let (_, past_the_end) = app
+        .send(request().path("/questions/search?q=traits&limit=2&offset=10"))
+        .await;
+    assert_eq!(past_the_end["total"], 3);
+    assert_eq!(past_the_end["results"], json!([]));
+}
+
+#[tokio::test]
+async fn search_leaves_out_held_questions_of_others() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    app.add_question(&alice, "Oh shoot, traits").await;
+
+    let (_, found) = app.send(request().path("/questions/search?q=traits")).await;
+    assert_eq!(found["total"], 0);
+
+    let (_, found) = app
+        .send(
+            request()
+                .path("/questions/search?q=traits")
+                .header("Authorization", &alice),
+        )
+        .await;
+    assert_eq!(found["total"], 1);
+}
+
+#[tokio::test]
+async fn search_needs_a_query() {
+    let app = App::new();
+
+    let (status, problem) = app.send(request().path("/questions/search?q=+")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code(&problem), "missing_parameter");
+}
+
+#[tokio::test]
+async fn question_list_can_be_filtered_and_sorted() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let bob = access_token(&app.sign_up("bob@example.com").await);
+    let bob_id = app
+        .store
+        .get_account("bob@example.com".to_string())
+        .await
+        .unwrap()
+        .id
+        .unwrap();
+
+    let mut ids = vec![];
+    for (token, tags) in [
+        (&alice, json!(["rust", "async"])),
+        (&alice, json!(["rust"])),
+        (&bob, json!(["go"])),
+    ] {
+        let (_, question) = app
+            .post_question(
+                token,
+                json!({ "title": "Title", "content": "Content", "tags": tags }),
+            )
+            .await;
+        ids.push(question["id"].clone());
+    }
+    app.add_answer(&bob, ids[1].as_i64().unwrap()).await;
+
+    assert_eq!(app.question_ids("tag=rust").await, ids[..2]);
+    assert_eq!(
+        app.question_ids("tag=rust,async&tag_match=all").await,
+        ids[..1]
+    );
+    assert_eq!(
+        app.question_ids(&format!("author={}", bob_id.0)).await,
+        ids[2..]
+    );
+    assert_eq!(app.question_ids("answered=true").await, ids[1..2]);
+    assert_eq!(app.question_ids("sort=newest").await[0], ids[2]);
+    assert_eq!(app.question_ids("sort=most_answered").await[0], ids[1]);
+}
+
+#[tokio::test]
+async fn unknown_filters_are_refused() {
+    let app = App::new();
+
+    let (status, problem) = app.send(request().path("/questions?tags=rust")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code(&problem), "unknown_parameter");
+}
+
+#[tokio::test]
+async fn question_pages_link_to_each_other() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let mut ids = vec![];
+    for _ in 0..5 {
+        let (_, question) = app.add_question(&alice, "Content").await;
+        ids.push(question["id"].clone());
+    }
+
+    let (_, first) = app.send(request().path("/questions?limit=2")).await;
+    assert_eq!(first["total"], 5);
+    assert_eq!(first["prev"], Value::Null);
+
+    let next = format!(
+        "/questions?limit=2&cursor={}",
+        first["next"].as_str().unwrap()
+    );
+    let (_, second) = app.send(request().path(&next)).await;
+    assert_eq!(second["items"][0]["id"], ids[2]);
+    assert_eq!(second["items"][1]["id"], ids[3]);
+
+    let prev = format!(
+        "/questions?limit=2&cursor={}",
+        second["prev"].as_str().unwrap()
+    );
+    let (_, back) = app.send(request().path(&prev)).await;
+    assert_eq!(back["items"], first["items"]);
+    assert_eq!(back["prev"], Value::Null);
+
+    let res = request().path(&next).reply(&app.routes).await;
+    let link = res.headers()["link"].to_str().unwrap();
+    assert!(link.contains("rel=\"first\""));
+    assert!(link.contains("rel=\"next\""));
+    assert!(link.contains("rel=\"prev\""));
+}
+
+#[tokio::test]
+async fn offset_pagination_returns_a_bare_list() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let mut ids = vec![];
+    for _ in 0..3 {
+        let (_, question) = app.add_question(&alice, "Content").await;
+        ids.push(question["id"].clone());
+    }
+
+    let (status, list) = app.send(request().path("/questions?offset=1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 2);
+    assert_eq!(list[0]["id"], ids[1]);
+}
+
+#[tokio::test]
+async fn question_lists_can_be_streamed_as_ndjson() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    for _ in 0..3 {
+        app.add_question(&alice, "Content").await;
+    }
+    app.add_question(&alice, "shoot").await;
+
+    // Streamed lists are whole unless limited, but leave out what isn't
+    // visible
+    let res = request()
+        .path("/questions")
+        .header("accept", "application/x-ndjson")
+        .reply(&app.routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(res.headers()["vary"], "accept");
+
+    let lines: Vec<Value> = std::str::from_utf8(res.body())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines
+        .iter()
+        .all(|question| question["content"] == "Content"));
+}
+
+#[tokio::test]
+async fn answer_lists_can_be_streamed_as_csv() {
+    let app = App::new();
+    let alice = access_token(&app.sign_up("alice@example.com").await);
+    let (_, question) = app.add_question(&alice, "Content").await;
+    let question_id = question["id"].as_i64().unwrap();
+    app.add_answer(&alice, question_id).await;
+    app.add_answer(&alice, question_id).await;
+
+    let res = request()
+        .path(&format!("/questions/{}/answers", question_id))
+        .header("accept", "text/csv;q=0.9, application/json;q=0.5")
+        .reply(&app.routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "text/csv; charset=utf-8");
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"answers.csv\""
+    );
+
+    let body = std::str::from_utf8(res.body()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines[0], "id,question_id,content");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].ends_with(&format!(",{},An answer", question_id)));
+}
+
+#[tokio::test]
+async fn streamed_lists_refuse_cursors_and_unknown_formats() {
+    let app = App::new();
+
+    let (status, problem) = app
+        .send(
+            request()
+                .path("/questions?cursor=abc")
+                .header("accept", "text/csv"),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(code(&problem), "invalid_parameter");
+
+    let (status, problem) = app
+        .send(
+            request()
+                .path("/questions")
+                .header("accept", "application/xml"),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(code(&problem), "not_acceptable");
+}
+
+#[tokio::test]
+async fn liveness_and_build_info_need_no_dependencies() {
+    let app = App::new();
+
+    let (status, health) = app.send(request().path("/health")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health["status"], "ok");
+
+    let (status, version) = app.send(request().path("/version")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert!(version["commit"].is_string());
+}
+
+#[tokio::test]
+async fn readiness_reports_every_dependency() {
+    let app = App::new();
+
+    let (status, ready) = app.send(request().path("/ready")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ready["status"], "ready");
+    for check in ["database", "migrations", "profanity", "shutdown"] {
+        assert_eq!(ready["checks"][check]["status"], "up", "{} is down", check);
+    }
+}
+
+#[tokio::test]
+async fn unreachable_profanity_api_only_degrades_readiness() {
+    let config = ApiConfig {
+        url: "http://127.0.0.1:1/bad_words".to_string(),
+        ..ApiConfig::default()
+    };
+    let checker = ApiLayerChecker::new(&config, "test-key".to_string()).unwrap();
+    let app = App::with_policy(ContentPolicy::new(
+        Arc::new(checker),
+        ModerationPolicy::Censor,
+        ModerationPolicy::Censor,
+    ));
+
+    let (status, ready) = app.send(request().path("/ready")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(ready["status"], "degraded");
+    assert_eq!(ready["checks"]["profanity"]["status"], "down");
+    assert_eq!(ready["checks"]["profanity"]["critical"], false);
+    assert!(ready["checks"]["profanity"]["error"].is_string());
+}
+
+#[tokio::test]
+async fn draining_instances_are_not_ready() {
+    let app = App::new();
+    app.draining.start();
+
+    let (status, ready) = app.send(request().path("/ready")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(ready["status"], "not_ready");
+    assert_eq!(ready["checks"]["shutdown"]["error"], "shutting down");
+}
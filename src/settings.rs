@@ -0,0 +1,580 @@
+use std::{
+    env, fmt, fs,
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+};
+
+use config::{Config, ConfigError, Environment, File};
+use serde::Deserialize;
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{
+    profanity::{ApiConfig, BreakerConfig, CacheConfig, ModerationPolicy},
+    shutdown::ShutdownConfig,
+    tls::TlsConfig,
+};
+
+/// Prefix of the environment variables overriding the configuration files,
+/// e.g. `APP_APP_PORT=8080` or `APP_PROFANITY_API__TIMEOUT_SECONDS=5` for
+/// a field of a table.
+const ENV_PREFIX: &str = "APP";
+
+/// Selects the overlay file read on top of the base configuration, e.g.
+/// `APP_ENV=prod` reads `setup.prod.toml` if it exists.
+const ENV_NAME_VAR: &str = "APP_ENV";
+
+/// Key length of PASETO v2 local tokens
+const PASETO_KEY_LENGTH: usize = 32;
+
+/// Which storage backend the routes are served from.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Postgres,
+    Memory,
+}
+
+/// Where posted content is checked for bad words.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfanityBackend {
+    /// The apilayer.com bad words API, see `[profanity_api]`
+    #[default]
+    Api,
+    /// A local wordlist file, for running without internet access
+    Wordlist,
+}
+
+/// How log lines are written to stdout.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregation
+    Json,
+}
+
+/// Deployment the overlay file is picked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeployEnvironment {
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl DeployEnvironment {
+    fn from_env() -> Result<Option<Self>, SettingsError> {
+        match env::var(ENV_NAME_VAR).as_deref() {
+            Err(_) | Ok("") => Ok(None),
+            Ok("dev") => Ok(Some(DeployEnvironment::Dev)),
+            Ok("staging") => Ok(Some(DeployEnvironment::Staging)),
+            Ok("prod") => Ok(Some(DeployEnvironment::Prod)),
+            Ok(other) => Err(SettingsError::Invalid(vec![format!(
+                "{} must be one of dev, staging or prod, not {:?}",
+                ENV_NAME_VAR, other
+            )])),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeployEnvironment::Dev => "dev",
+            DeployEnvironment::Staging => "staging",
+            DeployEnvironment::Prod => "prod",
+        }
+    }
+}
+
+fn default_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+fn default_profanity_wordlist() -> String {
+    "wordlist.txt".to_string()
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Args {
+    pub log_level: String,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    #[serde(default)]
+    pub profanity_backend: ProfanityBackend,
+    #[serde(default = "default_profanity_wordlist")]
+    pub profanity_wordlist: String,
+    #[serde(default)]
+    pub question_moderation: ModerationPolicy,
+    #[serde(default)]
+    pub answer_moderation: ModerationPolicy,
+    #[serde(default)]
+    pub profanity_api: ApiConfig,
+    #[serde(default)]
+    pub profanity_cache: CacheConfig,
+    #[serde(default)]
+    pub profanity_breaker: BreakerConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    pub database_host: String,
+    pub database_port: u16,
+    pub database_name: String,
+    #[serde(default = "default_bind_address")]
+    pub bind_address: IpAddr,
+    pub app_port: u16,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    pub database_username: String,
+    #[serde(default)]
+    database_password: String,
+    /// File to read the database password from instead
+    #[serde(default)]
+    database_password_file: Option<String>,
+    /// Key tokens are encrypted with, falls back to `PASETO_KEY`
+    #[serde(default)]
+    paseto_key: Option<String>,
+    /// File to read the token key from instead
+    #[serde(default)]
+    paseto_key_file: Option<String>,
+    /// Filled in from the fields above by [`Args::load`]
+    #[serde(skip)]
+    pub secrets: Secrets,
+}
+
+/// Secrets after they were read from files or the environment
+#[derive(Default, PartialEq)]
+pub struct Secrets {
+    pub database_password: String,
+    pub paseto_key: String,
+    /// Only set for the `api` profanity backend
+    pub profanity_api_key: Option<String>,
+}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secrets").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// A file couldn't be read or a value has the wrong type
+    Load(ConfigError),
+    /// The values were read, but don't make sense together
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Load(e) => write!(f, "Cannot load configuration: {}", e),
+            SettingsError::Invalid(errors) => {
+                write!(f, "Invalid configuration:")?;
+                for error in errors {
+                    write!(f, "\n  - {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<ConfigError> for SettingsError {
+    fn from(e: ConfigError) -> Self {
+        SettingsError::Load(e)
+    }
+}
+
+/// The value of `file` if set, otherwise `value`
+fn secret(name: &str, value: Option<&str>, file: Option<&str>) -> Result<Option<String>, String> {
+    match file {
+        Some(path) => fs::read_to_string(path)
+            .map(|secret| Some(secret.trim().to_string()))
+            .map_err(|e| format!("Cannot read {} from {}: {}", name, path, e)),
+        None => Ok(value.map(str::to_string)),
+    }
+}
+
+impl Args {
+    /// Reads the configuration in layers, each overriding the one before:
+    ///
+    /// 1. the base file at `path`, with or without the `.toml` extension
+    /// 2. the overlay `<path>.<APP_ENV>.toml`, if `APP_ENV` is set and the
+    ///    file exists
+    /// 3. `APP_`-prefixed environment variables, with `__` between a table
+    ///    and its fields
+    /// 4. `PORT`, as set by most hosting platforms
+    ///
+    /// Secrets are resolved and every value is validated, reporting all
+    /// problems at once. The token key and the bad words API key are only
+    /// needed to serve, so they're left out unless `serving` is set.
+    pub fn load(path: &str, serving: bool) -> Result<Args, SettingsError> {
+        let mut builder = Config::builder().add_source(File::with_name(path));
+
+        if let Some(environment) = DeployEnvironment::from_env()? {
+            let base = Path::new(path);
+            let base = match base.extension() {
+                Some(extension) if extension == "toml" => base.with_extension(""),
+                _ => base.to_path_buf(),
+            };
+            let overlay = format!("{}.{}", base.display(), environment.as_str());
+            builder = builder.add_source(File::with_name(&overlay).required(false));
+        }
+
+        let mut args = builder
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__"),
+            )
+            .build()?
+            .try_deserialize::<Args>()?;
+
+        let mut errors = vec![];
+        if let Ok(port) = env::var("PORT") {
+            match port.parse::<u16>() {
+                Ok(port) => args.app_port = port,
+                Err(e) => errors.push(format!("PORT {:?} is not a valid port: {}", port, e)),
+            }
+        }
+
+        args.resolve_secrets(serving, &mut errors);
+        args.validate(&mut errors);
+
+        if errors.is_empty() {
+            Ok(args)
+        } else {
+            Err(SettingsError::Invalid(errors))
+        }
+    }
+
+    /// Connection string for the `postgres` storage backend
+    pub fn database_url(&self) -> String {
+        format!(
+            "postgres://{username}:{password}@{host}:{port}/{name}",
+            username = self.database_username,
+            password = self.secrets.database_password,
+            host = self.database_host,
+            port = self.database_port,
+            name = self.database_name
+        )
+    }
+
+    fn resolve_secrets(&mut self, serving: bool, errors: &mut Vec<String>) {
+        match secret(
+            "database_password",
+            Some(&self.database_password),
+            self.database_password_file.as_deref(),
+        ) {
+            Ok(password) => self.secrets.database_password = password.unwrap_or_default(),
+            Err(e) => errors.push(e),
+        }
+
+        if !serving {
+            return;
+        }
+
+        let paseto_key = secret(
+            "paseto_key",
+            self.paseto_key.as_deref(),
+            self.paseto_key_file.as_deref(),
+        )
+        .map(|key| key.or_else(|| env::var("PASETO_KEY").ok()));
+        match paseto_key {
+            Ok(Some(key)) if key.len() == PASETO_KEY_LENGTH => self.secrets.paseto_key = key,
+            Ok(Some(key)) => errors.push(format!(
+                "paseto_key must be {} bytes long, not {}",
+                PASETO_KEY_LENGTH,
+                key.len()
+            )),
+            Ok(None) => errors.push(
+                "paseto_key is not set, use paseto_key_file, APP_PASETO_KEY or PASETO_KEY"
+                    .to_string(),
+            ),
+            Err(e) => errors.push(e),
+        }
+
+        if self.profanity_backend == ProfanityBackend::Api {
+            match self.profanity_api.api_key() {
+                Ok(key) => self.secrets.profanity_api_key = Some(key),
+                Err(e) => errors.push(format!("profanity_api: {}", e)),
+            }
+        }
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if let Err(e) = self.log_level.parse::<LevelFilter>() {
+            errors.push(format!("log_level {:?}: {}", self.log_level, e));
+        }
+
+        if self.storage_backend == StorageBackend::Postgres {
+            for (name, value) in [
+                ("database_host", &self.database_host),
+                ("database_name", &self.database_name),
+                ("database_username", &self.database_username),
+            ] {
+                if value.is_empty() {
+                    errors.push(format!("{} must not be empty", name));
+                }
+            }
+        }
+
+        match self.profanity_backend {
+            ProfanityBackend::Api => {
+                if reqwest::Url::parse(&self.profanity_api.url).is_err() {
+                    errors.push(format!(
+                        "profanity_api.url {:?} is not a valid URL",
+                        self.profanity_api.url
+                    ));
+                }
+                if self.profanity_api.timeout_seconds == 0 {
+                    errors.push("profanity_api.timeout_seconds must be at least 1".to_string());
+                }
+            }
+            ProfanityBackend::Wordlist => {
+                if let Err(e) = fs::metadata(&self.profanity_wordlist) {
+                    errors.push(format!(
+                        "Cannot read profanity_wordlist {}: {}",
+                        self.profanity_wordlist, e
+                    ));
+                }
+            }
+        }
+
+        let threshold = self.profanity_breaker.error_threshold;
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            errors.push(format!(
+                "profanity_breaker.error_threshold must be above 0 and at most 1, not {}",
+                threshold
+            ));
+        }
+        if self.profanity_breaker.window == 0 {
+            errors.push("profanity_breaker.window must be at least 1".to_string());
+        }
+
+        if self.tls.enabled {
+            if let Err(e) = self.tls.check() {
+                errors.push(e);
+            }
+            if self.tls.redirect_port == Some(self.app_port) {
+                errors.push(format!(
+                    "tls.redirect_port must differ from app_port {}",
+                    self.app_port
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    /// Loading reads the process environment, so tests changing it take
+    /// turns
+    static ENV: Mutex<()> = Mutex::new(());
+
+    const BASE: &str = r#"
+log_level = "info"
+storage_backend = "memory"
+profanity_backend = "wordlist"
+database_host = "localhost"
+database_port = 5432
+database_name = "rustwebdev"
+database_username = "postgres"
+app_port = 8080
+"#;
+
+    /// Writes `setup.toml` with `BASE` and `extra` into a fresh directory
+    /// and returns its path. Keys set in `extra` replace those of `BASE`.
+    fn setup(test: &str, extra: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("settings-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("setup.toml");
+        let base: String = BASE
+            .lines()
+            .filter(|line| {
+                let key = line.split(" = ").next().unwrap_or_default();
+                !extra
+                    .lines()
+                    .any(|extra| extra.starts_with(&format!("{} = ", key)))
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        fs::write(&path, format!("{}{}", base, extra)).unwrap();
+
+        path
+    }
+
+    fn clear_env() {
+        for name in [
+            ENV_NAME_VAR,
+            "PORT",
+            "PASETO_KEY",
+            "APP_APP_PORT",
+            "APP_PROFANITY_API__TIMEOUT_SECONDS",
+        ] {
+            env::remove_var(name);
+        }
+    }
+
+    fn load(path: &Path, serving: bool) -> Result<Args, SettingsError> {
+        Args::load(path.to_str().unwrap(), serving)
+    }
+
+    fn errors(path: &Path, serving: bool) -> Vec<String> {
+        match load(path, serving) {
+            Err(SettingsError::Invalid(errors)) => errors,
+            other => panic!("expected invalid settings, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn layers_override_each_other_in_order() {
+        let _env = ENV.lock();
+        clear_env();
+        let path = setup("layers", "");
+        fs::write(
+            path.with_file_name("setup.staging.toml"),
+            "app_port = 8081\nlog_level = \"debug\"\n",
+        )
+        .unwrap();
+
+        let args = load(&path, false).unwrap();
+        assert_eq!(args.app_port, 8080);
+        assert_eq!(args.profanity_api.timeout_seconds, 10);
+
+        env::set_var(ENV_NAME_VAR, "staging");
+        let args = load(&path, false).unwrap();
+        assert_eq!(args.app_port, 8081);
+        assert_eq!(args.log_level, "debug");
+
+        env::set_var("APP_APP_PORT", "8082");
+        env::set_var("APP_PROFANITY_API__TIMEOUT_SECONDS", "5");
+        let args = load(&path, false).unwrap();
+        assert_eq!(args.app_port, 8082);
+        assert_eq!(args.profanity_api.timeout_seconds, 5);
+        assert_eq!(args.log_level, "debug");
+
+        env::set_var("PORT", "9000");
+        assert_eq!(load(&path, false).unwrap().app_port, 9000);
+
+        // Overlays which don't exist are skipped
+        env::set_var(ENV_NAME_VAR, "prod");
+        env::remove_var("PORT");
+        assert_eq!(load(&path, false).unwrap().log_level, "info");
+        clear_env();
+    }
+
+    #[test]
+    fn unknown_environments_and_ports_are_refused() {
+        let _env = ENV.lock();
+        clear_env();
+        let path = setup("unknown", "");
+
+        env::set_var(ENV_NAME_VAR, "production");
+        assert!(errors(&path, false)[0].contains("dev, staging or prod"));
+        env::remove_var(ENV_NAME_VAR);
+
+        env::set_var("PORT", "http");
+        assert!(errors(&path, false)[0].starts_with("PORT \"http\""));
+        clear_env();
+    }
+
+    #[test]
+    fn all_problems_are_reported_at_once() {
+        let _env = ENV.lock();
+        clear_env();
+        let path = setup(
+            "problems",
+            r#"
+log_level = "loud"
+storage_backend = "postgres"
+database_host = ""
+profanity_backend = "api"
+
+[profanity_api]
+url = "not a url"
+timeout_seconds = 0
+
+[profanity_breaker]
+error_threshold = 0.0
+window = 0
+"#,
+        );
+
+        let errors = errors(&path, false);
+        assert_eq!(errors.len(), 6, "{:#?}", errors);
+        assert!(errors[0].starts_with("log_level \"loud\""));
+        assert_eq!(errors[1], "database_host must not be empty");
+        assert!(errors[2].starts_with("profanity_api.url"));
+        assert_eq!(
+            errors[3],
+            "profanity_api.timeout_seconds must be at least 1"
+        );
+        assert!(errors[4].starts_with("profanity_breaker.error_threshold"));
+        assert_eq!(errors[5], "profanity_breaker.window must be at least 1");
+    }
+
+    #[test]
+    fn serving_needs_the_token_and_api_keys() {
+        let _env = ENV.lock();
+        clear_env();
+        let path = setup(
+            "serving",
+            r#"
+profanity_backend = "api"
+
+[profanity_api]
+api_key_env = "SETTINGS_TEST_BAD_WORDS_API_KEY"
+"#,
+        );
+
+        assert!(load(&path, false).is_ok());
+        assert_eq!(
+            errors(&path, true),
+            [
+                "paseto_key is not set, use paseto_key_file, APP_PASETO_KEY or PASETO_KEY",
+                "profanity_api: SETTINGS_TEST_BAD_WORDS_API_KEY not set",
+            ]
+        );
+
+        env::set_var("PASETO_KEY", "too short");
+        assert_eq!(
+            errors(&path, true)[0],
+            "paseto_key must be 32 bytes long, not 9"
+        );
+        clear_env();
+    }
+
+    #[test]
+    fn secrets_are_read_from_files() {
+        let _env = ENV.lock();
+        clear_env();
+        let dir = setup("secrets", "").with_file_name("");
+        fs::write(dir.join("password"), "hunter2\n").unwrap();
+        fs::write(dir.join("key"), "RANDOM WORDS WINTER MACINTOSH PC\n").unwrap();
+        let path = setup(
+            "secrets",
+            &format!(
+                "database_password_file = {:?}\npaseto_key_file = {:?}\n",
+                dir.join("password"),
+                dir.join("key")
+            ),
+        );
+
+        let args = load(&path, true).unwrap();
+        assert_eq!(args.secrets.database_password, "hunter2");
+        assert_eq!(args.secrets.paseto_key, "RANDOM WORDS WINTER MACINTOSH PC");
+        assert!(args.database_url().contains(":hunter2@"));
+    }
+}
@@ -0,0 +1,845 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use super::{
+    AccountRepository, AnswerRepository, AnswerStream, HealthRepository, ModerationRepository,
+    QuestionRepository, QuestionStream, TokenRepository,
+};
+use crate::types::{
+    account::{Account, AccountId, Role, Viewer},
+    answer::{Answer, AnswerId, NewAnswer},
+    filter::{QuestionFilter, SortOrder, TagMatch},
+    moderation::{
+        ContentKind, ContentRef, ContentStatus, Decision, Flag, ModerationAction, QueueItem,
+    },
+    pagination::{Cursor, CursorKey, Direction, Page},
+    question::{LegacyQuestion, NewQuestion, Question, QuestionId},
+    revision::QuestionRevision,
+    search::{SearchResult, SearchResults},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use handle_errors::Error;
+use parking_lot::RwLock;
+
+#[derive(Debug, Clone)]
+struct QuestionRecord {
+    question: Question,
+    account_id: Option<AccountId>,
+    created_on: DateTime<Utc>,
+    status: ContentStatus,
+}
+
+impl QuestionRecord {
+    fn is_visible_to(&self, viewer: &Viewer) -> bool {
+        viewer.can_see(self.status, self.account_id.as_ref())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AnswerRecord {
+    answer: Answer,
+    account_id: AccountId,
+    created_on: DateTime<Utc>,
+    status: ContentStatus,
+}
+
+impl AnswerRecord {
+    fn is_visible_to(&self, viewer: &Viewer) -> bool {
+        viewer.can_see(self.status, Some(&self.account_id))
+    }
+}
+
+/// Storage backend which keeps everything in process memory. Used for
+/// testing the routes and for running the server without a database.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryStore {
+    questions: Arc<RwLock<HashMap<QuestionId, QuestionRecord>>>,
+    answers: Arc<RwLock<HashMap<AnswerId, AnswerRecord>>>,
+    accounts: Arc<RwLock<HashMap<String, Account>>>,
+    revoked_sessions: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    flags: Arc<RwLock<Vec<Flag>>>,
+    decisions: Arc<RwLock<Vec<Decision>>>,
+    revisions: Arc<RwLock<Vec<QuestionRevision>>>,
+    next_id: Arc<AtomicI32>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore {
+            next_id: Arc::new(AtomicI32::new(1)),
+            ..Default::default()
+        }
+    }
+
+    /// Create a store pre-populated with the questions from `questions.json`.
+    pub fn seeded() -> Self {
+        let store = InMemoryStore::new();
+        let file = include_str!("../../questions.json");
+        let seed: HashMap<String, LegacyQuestion> =
+            serde_json::from_str(file).expect("can't read questions.json");
+
+        let mut questions = store.questions.write();
+        for seed in seed.into_values() {
+            let id = seed.id.parse::<i32>().expect("question id is not a number");
+            questions.insert(
+                QuestionId(id),
+                QuestionRecord {
+                    question: Question {
+                        id: QuestionId(id),
+                        title: seed.title,
+                        content: seed.content,
+                        tags: seed.tags,
+                    },
+                    account_id: None,
+                    created_on: Utc::now(),
+                    status: ContentStatus::Published,
+                },
+            );
+            store.next_id.fetch_max(id + 1, Ordering::SeqCst);
+        }
+        drop(questions);
+
+        store
+    }
+
+    /// Answers to a question visible to `viewer`, oldest first
+    fn visible_answers(&self, question_id: i32, viewer: &Viewer) -> Vec<Answer> {
+        let mut answers: Vec<Answer> = self
+            .answers
+            .read()
+            .values()
+            .filter(|record| record.answer.question_id.0 == question_id)
+            .filter(|record| record.is_visible_to(viewer))
+            .map(|record| record.answer.clone())
+            .collect();
+        answers.sort_by_key(|answer| answer.id.0);
+
+        answers
+    }
+
+    fn next_id(&self) -> i32 {
+        self.next_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Questions matching `filter` and visible to `viewer` in the requested
+    /// sort order, each with its position for cursor pagination.
+    fn filtered_questions(
+        &self,
+        filter: &QuestionFilter,
+        viewer: &Viewer,
+    ) -> Vec<(Question, CursorKey)> {
+        let answers = self.answers.read();
+        let answer_count = |id: &QuestionId| {
+            answers
+                .values()
+                .filter(|record| {
+                    &record.answer.question_id == id && record.status == ContentStatus::Published
+                })
+                .count() as i64
+        };
+
+        let mut rows: Vec<(Question, CursorKey)> = self
+            .questions
+            .read()
+            .values()
+            .filter(|record| record.is_visible_to(viewer))
+            .filter(|record| {
+                let tags = record.question.tags.as_deref().unwrap_or_default();
+                filter.tags.is_empty()
+                    || match filter.tag_match {
+                        TagMatch::Any => filter.tags.iter().any(|tag| tags.contains(tag)),
+                        TagMatch::All => filter.tags.iter().all(|tag| tags.contains(tag)),
+                    }
+            })
+            .filter(|record| {
+                filter
+                    .author
+                    .is_none_or(|author| record.account_id == Some(AccountId(author)))
+            })
+            .filter(|record| {
+                filter
+                    .created_before
+                    .is_none_or(|before| record.created_on < before)
+            })
+            .filter(|record| {
+                filter
+                    .created_after
+                    .is_none_or(|after| record.created_on > after)
+            })
+            .filter(|record| {
+                filter
+                    .answered
+                    .is_none_or(|answered| (answer_count(&record.question.id) > 0) == answered)
+            })
+            .map(|record| {
+                let key = CursorKey {
+                    answer_count: match filter.sort {
+                        SortOrder::MostAnswered => answer_count(&record.question.id),
+                        _ => 0,
+                    },
+                    created_on: record.created_on.naive_utc(),
+                    id: record.question.id.0,
+                };
+                (record.question.clone(), key)
+            })
+            .collect();
+
+        match filter.sort {
+            SortOrder::Oldest => rows.sort_by(|(_, a), (_, b)| a.cmp(b)),
+            SortOrder::Newest | SortOrder::MostAnswered => rows.sort_by(|(_, a), (_, b)| b.cmp(a)),
+        }
+
+        rows
+    }
+}
+
+/// Lowercased words of `text`, the in-memory stand-in for a tsvector.
+/// Unlike Postgres there is no stemming or stop word removal.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Wrap every word of `text` contained in `terms` in `<b></b>`, mirroring
+/// the default output of Postgres' `ts_headline`.
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, highlighted: &mut String| {
+        if terms.contains(&word.to_lowercase()) {
+            highlighted.push_str(&format!("<b>{}</b>", word));
+        } else {
+            highlighted.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut highlighted);
+            highlighted.push(c);
+        }
+    }
+    flush(&mut word, &mut highlighted);
+
+    highlighted
+}
+
+#[async_trait]
+impl QuestionRepository for InMemoryStore {
+    async fn get_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Question>, Error> {
+        let questions = self
+            .filtered_questions(&filter, viewer)
+            .into_iter()
+            .map(|(question, _)| question)
+            .skip(offset as usize);
+        Ok(match limit {
+            Some(limit) => questions.take(limit as usize).collect(),
+            None => questions.collect(),
+        })
+    }
+
+    fn stream_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> QuestionStream {
+        // Everything is in memory already, so there is nothing to gain from
+        // producing the questions lazily
+        let questions = self
+            .filtered_questions(&filter, &viewer)
+            .into_iter()
+            .map(|(question, _)| Ok(question))
+            .skip(offset as usize);
+        let questions: Vec<_> = match limit {
+            Some(limit) => questions.take(limit as usize).collect(),
+            None => questions.collect(),
+        };
+
+        stream::iter(questions).boxed()
+    }
+
+    async fn get_questions_page(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        cursor: Option<Cursor>,
+        limit: u32,
+    ) -> Result<Page<Question>, Error> {
+        let rows = self.filtered_questions(&filter, viewer);
+        let total = rows.len() as i64;
+
+        let mut rows: Vec<(Question, CursorKey)> = match &cursor {
+            Some(cursor) => {
+                let ascending = Cursor::ascending(filter.sort, cursor.direction);
+                let mut rows: Vec<_> = rows
+                    .into_iter()
+                    .filter(|(_, key)| {
+                        if ascending {
+                            key > &cursor.key
+                        } else {
+                            key < &cursor.key
+                        }
+                    })
+                    .collect();
+                // Walk away from the cursor, as the database would
+                if cursor.direction == Direction::Before {
+                    rows.reverse();
+                }
+                rows
+            }
+            None => rows,
+        };
+        rows.truncate(limit as usize + 1);
+
+        Ok(Page::from_rows(
+            rows,
+            cursor.as_ref(),
+            filter.sort,
+            limit,
+            total,
+        ))
+    }
+
+    async fn get_question(&self, id: i32, viewer: &Viewer) -> Result<Question, Error> {
+        self.questions
+            .read()
+            .get(&QuestionId(id))
+            .filter(|record| record.is_visible_to(viewer))
+            .map(|record| record.question.clone())
+            .ok_or(Error::NotFound)
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<SearchResults, Error> {
+        let terms = words(query);
+        if terms.is_empty() {
+            return Ok(SearchResults {
+                total: 0,
+                results: vec![],
+            });
+        }
+
+        let answers = self.answers.read();
+        let mut results: Vec<SearchResult> = self
+            .questions
+            .read()
+            .values()
+            .filter(|record| record.is_visible_to(viewer))
+            .filter_map(|record| {
+                let question = &record.question;
+                let answer_content = answers
+                    .values()
+                    .filter(|answer| {
+                        answer.answer.question_id == question.id && answer.is_visible_to(viewer)
+                    })
+                    .map(|answer| answer.answer.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let title = words(&question.title);
+                let body = words(&format!("{} {}", question.content, answer_content));
+
+                // Every term has to occur somewhere, title hits weigh more
+                let mut rank = 0.0;
+                for term in &terms {
+                    let title_hits = title.iter().filter(|word| *word == term).count();
+                    let body_hits = body.iter().filter(|word| *word == term).count();
+                    if title_hits + body_hits == 0 {
+                        return None;
+                    }
+                    rank += (2 * title_hits + body_hits) as f32;
+                }
+
+                Some(SearchResult {
+                    question: question.clone(),
+                    rank: rank / (title.len() + body.len()) as f32,
+                    title_highlight: highlight(&question.title, &terms),
+                    snippet: highlight(
+                        format!("{} {}", question.content, answer_content).trim_end(),
+                        &terms,
+                    ),
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(a.question.id.0.cmp(&b.question.id.0))
+        });
+
+        let total = results.len() as i64;
+        let results = results.into_iter().skip(offset as usize);
+        Ok(SearchResults {
+            total,
+            results: match limit {
+                Some(limit) => results.take(limit as usize).collect(),
+                None => results.collect(),
+            },
+        })
+    }
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Question, Error> {
+        let question = Question {
+            id: QuestionId(self.next_id()),
+            title: question.title,
+            content: question.content,
+            tags: question.tags,
+        };
+
+        self.questions.write().insert(
+            question.id.clone(),
+            QuestionRecord {
+                question: question.clone(),
+                account_id: Some(account_id),
+                created_on: Utc::now(),
+                status,
+            },
+        );
+
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        status: ContentStatus,
+        editor: &AccountId,
+    ) -> Result<Question, Error> {
+        match self.questions.write().get_mut(&question.id) {
+            Some(record) => {
+                let previous = &record.question;
+                if (&previous.title, &previous.content, &previous.tags)
+                    != (&question.title, &question.content, &question.tags)
+                {
+                    let mut revisions = self.revisions.write();
+                    let revision = revisions
+                        .iter()
+                        .filter(|revision| revision.question_id == question.id)
+                        .count() as i64
+                        + 1;
+                    revisions.push(QuestionRevision {
+                        revision,
+                        question_id: question.id.clone(),
+                        title: previous.title.clone(),
+                        content: previous.content.clone(),
+                        tags: previous.tags.clone(),
+                        edited_by: Some(editor.clone()),
+                        edited_on: Utc::now(),
+                    });
+                }
+                record.question = question.clone();
+                record.status = status;
+                Ok(question)
+            }
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error> {
+        Ok(self
+            .revisions
+            .read()
+            .iter()
+            .filter(|revision| revision.question_id.0 == question_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_question(&self, id: i32) -> Result<bool, Error> {
+        if self.questions.write().remove(&QuestionId(id)).is_none() {
+            return Ok(false);
+        }
+        // Like the foreign key in the database, answers go with the question
+        self.answers
+            .write()
+            .retain(|_, record| record.answer.question_id.0 != id);
+        self.revisions
+            .write()
+            .retain(|revision| revision.question_id.0 != id);
+        Ok(true)
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(self
+            .questions
+            .read()
+            .get(&QuestionId(question_id))
+            .map_or(false, |record| {
+                record.account_id.as_ref() == Some(account_id)
+            }))
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for InMemoryStore {
+    async fn get_answers(&self, question_id: i32, viewer: &Viewer) -> Result<Vec<Answer>, Error> {
+        Ok(self.visible_answers(question_id, viewer))
+    }
+
+    fn stream_answers(&self, question_id: i32, viewer: Viewer) -> AnswerStream {
+        let answers = self.visible_answers(question_id, &viewer);
+        stream::iter(answers.into_iter().map(Ok)).boxed()
+    }
+
+    async fn get_answer(&self, id: i32, viewer: &Viewer) -> Result<Answer, Error> {
+        self.answers
+            .read()
+            .get(&AnswerId(id))
+            .filter(|record| record.is_visible_to(viewer))
+            .map(|record| record.answer.clone())
+            .ok_or(Error::NotFound)
+    }
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Answer, Error> {
+        if !self.questions.read().contains_key(&answer.question_id) {
+            return Err(Error::NotFound);
+        }
+
+        let answer = Answer {
+            id: AnswerId(self.next_id()),
+            content: answer.content,
+            question_id: answer.question_id,
+        };
+
+        self.answers.write().insert(
+            answer.id.clone(),
+            AnswerRecord {
+                answer: answer.clone(),
+                account_id,
+                created_on: Utc::now(),
+                status,
+            },
+        );
+
+        Ok(answer)
+    }
+
+    async fn update_answer(&self, answer: Answer, status: ContentStatus) -> Result<Answer, Error> {
+        match self.answers.write().get_mut(&answer.id) {
+            Some(record) => {
+                record.answer.content = answer.content;
+                record.status = status;
+                Ok(record.answer.clone())
+            }
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn delete_answer(&self, id: i32) -> Result<bool, Error> {
+        Ok(self.answers.write().remove(&AnswerId(id)).is_some())
+    }
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error> {
+        Ok(self
+            .answers
+            .read()
+            .get(&AnswerId(answer_id))
+            .map_or(false, |record| &record.account_id == account_id))
+    }
+}
+
+#[async_trait]
+impl AccountRepository for InMemoryStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write();
+        if accounts.contains_key(&account.email) {
+            return Err(Error::AccountAlreadyExists);
+        }
+
+        let account = Account {
+            id: Some(AccountId(self.next_id())),
+            ..account
+        };
+        accounts.insert(account.email.clone(), account);
+
+        Ok(true)
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        self.accounts
+            .read()
+            .get(&email)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, Error> {
+        self.accounts
+            .read()
+            .values()
+            .find(|account| account.id == Some(AccountId(id)))
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error> {
+        let mut accounts: Vec<Account> = self.accounts.read().values().cloned().collect();
+        accounts.sort_by_key(|account| account.id.as_ref().map(|id| id.0));
+
+        Ok(accounts)
+    }
+
+    async fn set_account_role(&self, id: i32, role: Role) -> Result<Account, Error> {
+        self.accounts
+            .write()
+            .values_mut()
+            .find(|account| account.id == Some(AccountId(id)))
+            .map(|account| {
+                account.role = role;
+                account.clone()
+            })
+            .ok_or(Error::NotFound)
+    }
+
+    async fn set_account_password(&self, id: i32, password: String) -> Result<bool, Error> {
+        Ok(self
+            .accounts
+            .write()
+            .values_mut()
+            .find(|account| account.id == Some(AccountId(id)))
+            .map(|account| account.password = password)
+            .is_some())
+    }
+
+    async fn delete_account(&self, id: i32) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write();
+        let before = accounts.len();
+        accounts.retain(|_, account| account.id != Some(AccountId(id)));
+
+        Ok(accounts.len() < before)
+    }
+}
+
+#[async_trait]
+impl TokenRepository for InMemoryStore {
+    async fn revoke_session(&self, sid: &str, expires: DateTime<Utc>) -> Result<bool, Error> {
+        let mut revoked = self.revoked_sessions.write();
+        let now = Utc::now();
+        revoked.retain(|_, expires| *expires >= now);
+
+        match revoked.entry(sid.to_string()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(expires);
+                Ok(true)
+            }
+        }
+    }
+
+    async fn is_session_revoked(&self, sid: &str) -> Result<bool, Error> {
+        Ok(self.revoked_sessions.read().contains_key(sid))
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for InMemoryStore {
+    async fn add_flag(
+        &self,
+        target: ContentRef,
+        account_id: AccountId,
+        reason: String,
+    ) -> Result<Flag, Error> {
+        self.get_content_status(target).await?;
+
+        let mut flags = self.flags.write();
+        if flags.iter().any(|flag| {
+            flag.target == target
+                && flag.account_id.as_ref() == Some(&account_id)
+                && flag.decision_id.is_none()
+        }) {
+            return Err(Error::AlreadyFlagged);
+        }
+
+        let flag = Flag {
+            id: self.next_id(),
+            target,
+            account_id: Some(account_id),
+            reason,
+            created_on: Utc::now(),
+            decision_id: None,
+        };
+        flags.push(flag.clone());
+
+        Ok(flag)
+    }
+
+    async fn get_moderation_queue(&self) -> Result<Vec<QueueItem>, Error> {
+        let flags = self.flags.read();
+        let open_flags = |target: ContentRef| -> Vec<Flag> {
+            flags
+                .iter()
+                .filter(|flag| flag.target == target && flag.decision_id.is_none())
+                .cloned()
+                .collect()
+        };
+
+        let mut queue: Vec<(DateTime<Utc>, QueueItem)> = vec![];
+        for record in self.questions.read().values() {
+            let target = ContentRef {
+                kind: ContentKind::Question,
+                id: record.question.id.0,
+            };
+            let flags = open_flags(target);
+            if record.status == ContentStatus::Pending || !flags.is_empty() {
+                queue.push((
+                    record.created_on,
+                    QueueItem {
+                        target,
+                        question_id: record.question.id.0,
+                        title: Some(record.question.title.clone()),
+                        content: record.question.content.clone(),
+                        status: record.status,
+                        account_id: record.account_id.clone(),
+                        flags,
+                    },
+                ));
+            }
+        }
+        for record in self.answers.read().values() {
+            let target = ContentRef {
+                kind: ContentKind::Answer,
+                id: record.answer.id.0,
+            };
+            let flags = open_flags(target);
+            if record.status == ContentStatus::Pending || !flags.is_empty() {
+                queue.push((
+                    record.created_on,
+                    QueueItem {
+                        target,
+                        question_id: record.answer.question_id.0,
+                        title: None,
+                        content: record.answer.content.clone(),
+                        status: record.status,
+                        account_id: Some(record.account_id.clone()),
+                        flags,
+                    },
+                ));
+            }
+        }
+        queue.sort_by(|(a_created, a), (b_created, b)| {
+            a_created.cmp(b_created).then(a.target.id.cmp(&b.target.id))
+        });
+
+        Ok(queue.into_iter().map(|(_, item)| item).collect())
+    }
+
+    async fn get_content_status(&self, target: ContentRef) -> Result<ContentStatus, Error> {
+        let status = match target.kind {
+            ContentKind::Question => self
+                .questions
+                .read()
+                .get(&QuestionId(target.id))
+                .map(|record| record.status),
+            ContentKind::Answer => self
+                .answers
+                .read()
+                .get(&AnswerId(target.id))
+                .map(|record| record.status),
+        };
+
+        status.ok_or(Error::NotFound)
+    }
+
+    async fn set_content_status(
+        &self,
+        target: ContentRef,
+        status: ContentStatus,
+    ) -> Result<bool, Error> {
+        let found = match target.kind {
+            ContentKind::Question => self
+                .questions
+                .write()
+                .get_mut(&QuestionId(target.id))
+                .map(|record| record.status = status),
+            ContentKind::Answer => self
+                .answers
+                .write()
+                .get_mut(&AnswerId(target.id))
+                .map(|record| record.status = status),
+        };
+
+        Ok(found.is_some())
+    }
+
+    async fn add_decision(
+        &self,
+        target: ContentRef,
+        moderator_id: AccountId,
+        action: ModerationAction,
+        note: Option<String>,
+    ) -> Result<Decision, Error> {
+        let decision = Decision {
+            id: self.next_id(),
+            target,
+            moderator_id,
+            action,
+            note,
+            created_on: Utc::now(),
+        };
+
+        for flag in self.flags.write().iter_mut() {
+            if flag.target == target && flag.decision_id.is_none() {
+                flag.decision_id = Some(decision.id);
+            }
+        }
+        self.decisions.write().push(decision.clone());
+
+        Ok(decision)
+    }
+}
+
+/// Memory is always there and has no schema to migrate.
+#[async_trait]
+impl HealthRepository for InMemoryStore {
+    async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn pending_migrations(&self) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
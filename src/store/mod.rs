@@ -0,0 +1,231 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::types::{
+    account::{Account, AccountId, Role, Viewer},
+    answer::{Answer, NewAnswer},
+    filter::QuestionFilter,
+    moderation::{ContentRef, ContentStatus, Decision, Flag, ModerationAction, QueueItem},
+    pagination::{Cursor, Page},
+    question::{NewQuestion, Question},
+    revision::QuestionRevision,
+    search::SearchResults,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use handle_errors::Error;
+
+pub mod memory;
+pub mod postgres;
+
+pub use memory::InMemoryStore;
+pub use postgres::Store;
+
+/// Questions as they are read from the backend, see
+/// [`QuestionRepository::stream_questions`]
+pub type QuestionStream = BoxStream<'static, Result<Question, Error>>;
+
+/// Answers as they are read from the backend, see
+/// [`AnswerRepository::stream_answers`]
+pub type AnswerStream = BoxStream<'static, Result<Answer, Error>>;
+
+/// Shared handle to whichever storage backend the server was started with.
+/// This is what gets injected into the route handlers.
+pub type DynStore = Arc<dyn Repository>;
+
+/// Read methods take the [`Viewer`] of the request, so pending content is
+/// only returned to its author and moderators.
+#[async_trait]
+pub trait QuestionRepository: Send + Sync {
+    async fn get_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Question>, Error>;
+
+    /// Like [`QuestionRepository::get_questions`], but hands out each
+    /// question as soon as it was read, so exports of the whole list aren't
+    /// held in memory.
+    fn stream_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> QuestionStream;
+
+    /// Keyset paginated variant of [`QuestionRepository::get_questions`],
+    /// returning up to `limit` questions next to `cursor`.
+    async fn get_questions_page(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        cursor: Option<Cursor>,
+        limit: u32,
+    ) -> Result<Page<Question>, Error>;
+
+    async fn get_question(&self, id: i32, viewer: &Viewer) -> Result<Question, Error>;
+
+    /// Full-text search over question titles, contents and their answers,
+    /// best matches first.
+    async fn search_questions(
+        &self,
+        query: &str,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<SearchResults, Error>;
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Question, Error>;
+
+    /// Replaces title, content and tags, keeping the previous ones as a
+    /// revision made by `editor` if any of them changed.
+    async fn update_question(
+        &self,
+        question: Question,
+        status: ContentStatus,
+        editor: &AccountId,
+    ) -> Result<Question, Error>;
+
+    /// Earlier versions of the question, oldest first.
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error>;
+
+    async fn delete_question(&self, id: i32) -> Result<bool, Error>;
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+}
+
+#[async_trait]
+pub trait AnswerRepository: Send + Sync {
+    async fn get_answers(&self, question_id: i32, viewer: &Viewer) -> Result<Vec<Answer>, Error>;
+
+    /// Like [`AnswerRepository::get_answers`], but hands out each answer as
+    /// soon as it was read.
+    fn stream_answers(&self, question_id: i32, viewer: Viewer) -> AnswerStream;
+
+    async fn get_answer(&self, id: i32, viewer: &Viewer) -> Result<Answer, Error>;
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Answer, Error>;
+
+    async fn update_answer(&self, answer: Answer, status: ContentStatus) -> Result<Answer, Error>;
+
+    async fn delete_answer(&self, id: i32) -> Result<bool, Error>;
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error>;
+}
+
+#[async_trait]
+pub trait AccountRepository: Send + Sync {
+    async fn add_account(&self, account: Account) -> Result<bool, Error>;
+
+    async fn get_account(&self, email: String) -> Result<Account, Error>;
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, Error>;
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error>;
+
+    async fn set_account_role(&self, id: i32, role: Role) -> Result<Account, Error>;
+
+    /// Replaces the password hash, returning whether the account exists.
+    async fn set_account_password(&self, id: i32, password: String) -> Result<bool, Error>;
+
+    async fn delete_account(&self, id: i32) -> Result<bool, Error>;
+}
+
+/// Server-side list of revoked sessions, consulted on every authenticated
+/// request.
+#[async_trait]
+pub trait TokenRepository: Send + Sync {
+    /// Returns `false` if the session was already revoked, which makes
+    /// revoking usable as an atomic check for single use tokens.
+    async fn revoke_session(&self, sid: &str, expires: DateTime<Utc>) -> Result<bool, Error>;
+
+    async fn is_session_revoked(&self, sid: &str) -> Result<bool, Error>;
+}
+
+/// User reports of questions and answers, and the decisions moderators made
+/// on them.
+#[async_trait]
+pub trait ModerationRepository: Send + Sync {
+    /// Fails with [`Error::NotFound`] if the content doesn't exist and with
+    /// [`Error::AlreadyFlagged`] if the account has an open flag on it.
+    async fn add_flag(
+        &self,
+        target: ContentRef,
+        account_id: AccountId,
+        reason: String,
+    ) -> Result<Flag, Error>;
+
+    /// Content which is held for review or has open flags, oldest first.
+    async fn get_moderation_queue(&self) -> Result<Vec<QueueItem>, Error>;
+
+    async fn get_content_status(&self, target: ContentRef) -> Result<ContentStatus, Error>;
+
+    async fn set_content_status(
+        &self,
+        target: ContentRef,
+        status: ContentStatus,
+    ) -> Result<bool, Error>;
+
+    /// Record a decision, resolving all open flags of the content.
+    async fn add_decision(
+        &self,
+        target: ContentRef,
+        moderator_id: AccountId,
+        action: ModerationAction,
+        note: Option<String>,
+    ) -> Result<Decision, Error>;
+}
+
+/// Checks for the readiness probe.
+#[async_trait]
+pub trait HealthRepository: Send + Sync {
+    /// Whether the backend can answer queries.
+    async fn ping(&self) -> Result<(), Error>;
+
+    /// Number of schema migrations which weren't applied yet.
+    async fn pending_migrations(&self) -> Result<usize, Error>;
+}
+
+/// Everything the routes need from a storage backend. Implemented
+/// automatically for any type providing all of the repository traits.
+pub trait Repository:
+    QuestionRepository
+    + AnswerRepository
+    + AccountRepository
+    + TokenRepository
+    + ModerationRepository
+    + HealthRepository
+    + Debug
+{
+}
+
+impl<T> Repository for T where
+    T: QuestionRepository
+        + AnswerRepository
+        + AccountRepository
+        + TokenRepository
+        + ModerationRepository
+        + HealthRepository
+        + Debug
+{
+}
@@ -0,0 +1,1379 @@
+use std::collections::HashMap;
+
+use super::{
+    AccountRepository, AnswerRepository, AnswerStream, HealthRepository, ModerationRepository,
+    QuestionRepository, QuestionStream, TokenRepository,
+};
+use crate::types::{
+    account::{Account, AccountId, Role, Viewer},
+    answer::{Answer, AnswerId, NewAnswer},
+    filter::{QuestionFilter, TagMatch},
+    moderation::{
+        ContentKind, ContentRef, ContentStatus, Decision, Flag, ModerationAction, QueueItem,
+    },
+    pagination::{Cursor, CursorKey, Direction, Page},
+    question::{NewQuestion, Question, QuestionId},
+    revision::QuestionRevision,
+    search::{SearchResult, SearchResults},
+    transfer::{AnswerTransfer, ConflictMode, ImportReport, QuestionTransfer},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use handle_errors::Error;
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    postgres::{PgArguments, PgPoolOptions, PgRow},
+    query::Query,
+    PgPool, Postgres, Row,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Rows read ahead of the client when streaming
+const STREAM_BUFFER: usize = 64;
+
+/// Questions matching the filter parameters `$1` to `$6` and visible to the
+/// viewer in `$7` and `$8`, all bound by [`bind_filter`], together with
+/// their number of published answers. Every filter is optional, a NULL
+/// parameter disables its condition.
+const FILTERED_QUESTIONS: &str = "SELECT q.id, q.title, q.content, q.tags, q.created_on,
+        COALESCE(a.answer_count, 0) AS answer_count
+    FROM questions q
+    LEFT JOIN (
+        SELECT corresponding_question, COUNT(*) AS answer_count
+        FROM answers
+        WHERE status = 'published'
+        GROUP BY corresponding_question
+    ) a ON a.corresponding_question = q.id
+    WHERE ($1::text[] IS NULL
+            OR ($2 AND q.tags @> $1)
+            OR (NOT $2 AND q.tags && $1))
+        AND ($3::integer IS NULL OR q.account_id = $3)
+        AND ($4::timestamptz IS NULL OR q.created_on < $4)
+        AND ($5::timestamptz IS NULL OR q.created_on > $5)
+        AND ($6::boolean IS NULL OR (COALESCE(a.answer_count, 0) > 0) = $6)
+        AND (q.status = 'published' OR $7 OR q.account_id = $8)";
+
+fn bind_filter<'q>(
+    query: Query<'q, Postgres, PgArguments>,
+    filter: &QuestionFilter,
+    viewer: &Viewer,
+) -> Query<'q, Postgres, PgArguments> {
+    query
+        .bind(if filter.tags.is_empty() {
+            None
+        } else {
+            Some(filter.tags.clone())
+        })
+        .bind(filter.tag_match == TagMatch::All)
+        .bind(filter.author)
+        .bind(filter.created_before)
+        .bind(filter.created_after)
+        .bind(filter.answered)
+        .bind(viewer.moderator)
+        .bind(viewer.account_id.as_ref().map(|id| id.0))
+}
+
+/// [`FILTERED_QUESTIONS`] in the requested sort order, `$9`, with the limit
+/// and offset in `$10` and `$11`
+fn sorted_questions_query() -> String {
+    format!(
+        "SELECT * FROM ({}) q
+        ORDER BY
+            CASE WHEN $9 = 'most_answered' THEN answer_count END DESC,
+            CASE WHEN $9 = 'oldest' THEN created_on END ASC,
+            CASE WHEN $9 <> 'oldest' THEN created_on END DESC,
+            CASE WHEN $9 = 'oldest' THEN id END ASC,
+            CASE WHEN $9 <> 'oldest' THEN id END DESC
+        LIMIT $10 OFFSET $11",
+        FILTERED_QUESTIONS
+    )
+}
+
+fn question_from_row(row: PgRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+    }
+}
+
+/// Answers to the question in `$1` visible to the viewer in `$2` and `$3`,
+/// oldest first
+const QUESTION_ANSWERS: &str = "SELECT id, content, corresponding_question FROM answers
+    WHERE corresponding_question = $1
+        AND (status = 'published' OR $2 OR account_id = $3)
+    ORDER BY created_on";
+
+fn answer_from_row(row: PgRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("corresponding_question")),
+    }
+}
+
+/// Sends `rows` on to a streamed response until they run out, the first
+/// error or the client going away
+async fn forward<T, S>(mut rows: S, tx: mpsc::Sender<Result<T, Error>>)
+where
+    S: Stream<Item = Result<T, sqlx::Error>> + Unpin,
+{
+    while let Some(row) = rows.next().await {
+        let failed = row.is_err();
+        let row = row.map_err(|e| {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            Error::DatabaseQueryError(e)
+        });
+        if tx.send(row).await.is_err() || failed {
+            break;
+        }
+    }
+}
+
+/// Table holding the given kind of content
+fn content_table(kind: ContentKind) -> &'static str {
+    match kind {
+        ContentKind::Question => "questions",
+        ContentKind::Answer => "answers",
+    }
+}
+
+/// Statuses are only written by the store. One it doesn't know, e.g. from
+/// a newer version, is taken as the default rather than failing a whole
+/// export or moderation queue.
+fn stored_status(status: String) -> ContentStatus {
+    status.parse().unwrap_or_default()
+}
+
+fn content_ref(row: &PgRow, kind: &str, id: &str) -> ContentRef {
+    ContentRef {
+        kind: row
+            .get::<String, _>(kind)
+            .parse()
+            .expect("content type is only written by the store"),
+        id: row.get(id),
+    }
+}
+
+fn flag_from_row(row: &PgRow) -> Flag {
+    Flag {
+        id: row.get("id"),
+        target: content_ref(row, "content_type", "content_id"),
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        reason: row.get("reason"),
+        created_on: row.get("created_on"),
+        decision_id: row.get("decision_id"),
+    }
+}
+
+/// The schema migrations in `migrations/`, embedded into the binary
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Clone, Debug)]
+pub struct Store {
+    pub pool: PgPool,
+}
+
+impl Store {
+    pub async fn new(connection_string: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(connection_string)
+            .await?;
+
+        Ok(Store { pool })
+    }
+}
+
+#[async_trait]
+impl QuestionRepository for Store {
+    async fn get_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Question>, Error> {
+        let query = sorted_questions_query();
+
+        match bind_filter(sqlx::query(&query), &filter, viewer)
+            .bind(filter.sort.as_str())
+            .bind(limit)
+            .bind(offset)
+            .map(question_from_row)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    fn stream_questions(
+        &self,
+        filter: QuestionFilter,
+        viewer: Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> QuestionStream {
+        // The rows borrow the query, so they are read in a task of their own
+        // which holds on to it. The bounded channel keeps the task from
+        // reading ahead of a slow client, and it stops once the client is gone.
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let query = sorted_questions_query();
+            let rows = bind_filter(sqlx::query(&query), &filter, &viewer)
+                .bind(filter.sort.as_str())
+                .bind(limit)
+                .bind(offset)
+                .map(question_from_row)
+                .fetch(&pool);
+
+            forward(rows, tx).await;
+        });
+
+        ReceiverStream::new(rx).boxed()
+    }
+
+    async fn get_questions_page(
+        &self,
+        filter: QuestionFilter,
+        viewer: &Viewer,
+        cursor: Option<Cursor>,
+        limit: u32,
+    ) -> Result<Page<Question>, Error> {
+        let total = match bind_filter(
+            sqlx::query(&format!(
+                "SELECT COUNT(*) AS total FROM ({}) q",
+                FILTERED_QUESTIONS
+            )),
+            &filter,
+            viewer,
+        )
+        .map(|row: PgRow| row.get::<i64, _>("total"))
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(total) => total,
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+
+        // Rows are walked away from the cursor, which means backwards
+        // through the requested sort order when paging to previous rows
+        let ascending = Cursor::ascending(
+            filter.sort,
+            cursor.as_ref().map_or(Direction::After, |c| c.direction),
+        );
+        let query = format!(
+            "SELECT * FROM (
+                SELECT *, CASE WHEN $9 = 'most_answered' THEN answer_count ELSE 0 END AS rank
+                FROM ({}) q
+            ) q
+            WHERE $10::timestamp IS NULL
+                OR ($13 AND (rank, created_on, id) > ($12, $10, $11))
+                OR (NOT $13 AND (rank, created_on, id) < ($12, $10, $11))
+            ORDER BY
+                CASE WHEN $13 THEN rank END ASC,
+                CASE WHEN $13 THEN created_on END ASC,
+                CASE WHEN $13 THEN id END ASC,
+                CASE WHEN NOT $13 THEN rank END DESC,
+                CASE WHEN NOT $13 THEN created_on END DESC,
+                CASE WHEN NOT $13 THEN id END DESC
+            LIMIT $14",
+            FILTERED_QUESTIONS
+        );
+
+        let key = cursor.as_ref().map(|cursor| &cursor.key);
+        match bind_filter(sqlx::query(&query), &filter, viewer)
+            .bind(filter.sort.as_str())
+            .bind(key.map(|key| key.created_on))
+            .bind(key.map(|key| key.id))
+            .bind(key.map_or(0, |key| key.answer_count))
+            .bind(ascending)
+            .bind(limit as i64 + 1)
+            .map(|row: PgRow| {
+                (
+                    Question {
+                        id: QuestionId(row.get("id")),
+                        title: row.get("title"),
+                        content: row.get("content"),
+                        tags: row.get("tags"),
+                    },
+                    CursorKey {
+                        answer_count: row.get("rank"),
+                        created_on: row.get("created_on"),
+                        id: row.get("id"),
+                    },
+                )
+            })
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(rows) => Ok(Page::from_rows(
+                rows,
+                cursor.as_ref(),
+                filter.sort,
+                limit,
+                total,
+            )),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question(&self, id: i32, viewer: &Viewer) -> Result<Question, Error> {
+        match sqlx::query(
+            "SELECT * FROM questions
+            WHERE id = $1 AND (status = 'published' OR $2 OR account_id = $3)",
+        )
+        .bind(id)
+        .bind(viewer.moderator)
+        .bind(viewer.account_id.as_ref().map(|id| id.0))
+        .map(|row: PgRow| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+        })
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        viewer: &Viewer,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<SearchResults, Error> {
+        match sqlx::query(
+            // The count comes from all matches, so it's also right for pages
+            // past the end, which have no rows to carry it
+            "WITH matches AS (
+                SELECT q.id, q.title, q.content, q.tags, query,
+                    ts_rank(q.search_vector, query) + COALESCE(a.rank, 0) AS rank,
+                    a.content AS answer_content
+                FROM questions q
+                CROSS JOIN websearch_to_tsquery('english', $1) query
+                LEFT JOIN LATERAL (
+                    SELECT string_agg(content, ' ') AS content,
+                        MAX(ts_rank(search_vector, query)) AS rank
+                    FROM answers
+                    WHERE corresponding_question = q.id AND search_vector @@ query
+                        AND (status = 'published' OR $4 OR account_id = $5)
+                ) a ON true
+                WHERE (q.search_vector @@ query OR a.rank IS NOT NULL)
+                    AND (q.status = 'published' OR $4 OR q.account_id = $5)
+            ),
+            page AS (
+                SELECT * FROM matches
+                ORDER BY rank DESC, id
+                LIMIT $2 OFFSET $3
+            )
+            SELECT total.count AS total, page.id, page.title, page.content, page.tags, page.rank,
+                ts_headline('english', page.title, page.query, 'HighlightAll=true')
+                    AS title_highlight,
+                ts_headline(
+                    'english',
+                    page.content || ' ' || COALESCE(page.answer_content, ''),
+                    page.query,
+                    'MaxFragments=2, MaxWords=20, MinWords=5'
+                ) AS snippet
+            FROM (SELECT COUNT(*) AS count FROM matches) total
+            LEFT JOIN page ON true
+            ORDER BY page.rank DESC, page.id",
+        )
+        .bind(query)
+        .bind(limit)
+        .bind(offset)
+        .bind(viewer.moderator)
+        .bind(viewer.account_id.as_ref().map(|id| id.0))
+        .map(|row: PgRow| {
+            // An empty page is a single row with only the total
+            let result = row.get::<Option<i32>, _>("id").map(|id| SearchResult {
+                question: Question {
+                    id: QuestionId(id),
+                    title: row.get("title"),
+                    content: row.get("content"),
+                    tags: row.get("tags"),
+                },
+                rank: row.get("rank"),
+                title_highlight: row.get("title_highlight"),
+                snippet: row.get("snippet"),
+            });
+            (row.get::<i64, _>("total"), result)
+        })
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(rows) => Ok(SearchResults {
+                total: rows.first().map_or(0, |(total, _)| *total),
+                results: rows.into_iter().filter_map(|(_, result)| result).collect(),
+            }),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "INSERT INTO questions (title, content, tags, account_id, status)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, title, content, tags",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(|row| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+        })
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        status: ContentStatus,
+        editor: &AccountId,
+    ) -> Result<Question, Error> {
+        // Both statements see the question as it was before the update
+        match sqlx::query(
+            "WITH revision AS (
+                INSERT INTO question_revisions (question_id, account_id, title, content, tags)
+                SELECT id, $6, title, content, tags FROM questions
+                WHERE id = $5 AND (title, content, tags) IS DISTINCT FROM ($1, $2, $3)
+            )
+            UPDATE questions
+            SET
+                title = $1,
+                content = $2,
+                tags = $3,
+                status = $4
+            WHERE id = $5
+            RETURNING id, title, content, tags",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(status.as_str())
+        .bind(question.id.0)
+        .bind(editor.0)
+        .map(|row| Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+        })
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question_revisions(
+        &self,
+        question_id: i32,
+    ) -> Result<Vec<QuestionRevision>, Error> {
+        match sqlx::query(
+            "SELECT
+                ROW_NUMBER() OVER (ORDER BY id) AS revision,
+                question_id, account_id, title, content, tags, created_on
+            FROM question_revisions
+            WHERE question_id = $1
+            ORDER BY id",
+        )
+        .bind(question_id)
+        .map(|row: PgRow| QuestionRevision {
+            revision: row.get("revision"),
+            question_id: QuestionId(row.get("question_id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            edited_by: row.get::<Option<i32>, _>("account_id").map(AccountId),
+            edited_on: row.get("created_on"),
+        })
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(revisions) => Ok(revisions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_question(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM questions WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query("SELECT * from questions where id = $1 and account_id = $2")
+            .bind(question_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(question) => Ok(question.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for Store {
+    async fn get_answers(&self, question_id: i32, viewer: &Viewer) -> Result<Vec<Answer>, Error> {
+        match sqlx::query(QUESTION_ANSWERS)
+            .bind(question_id)
+            .bind(viewer.moderator)
+            .bind(viewer.account_id.as_ref().map(|id| id.0))
+            .map(answer_from_row)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    fn stream_answers(&self, question_id: i32, viewer: Viewer) -> AnswerStream {
+        // See `stream_questions`
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let rows = sqlx::query(QUESTION_ANSWERS)
+                .bind(question_id)
+                .bind(viewer.moderator)
+                .bind(viewer.account_id.as_ref().map(|id| id.0))
+                .map(answer_from_row)
+                .fetch(&pool);
+            forward(rows, tx).await;
+        });
+
+        ReceiverStream::new(rx).boxed()
+    }
+
+    async fn get_answer(&self, id: i32, viewer: &Viewer) -> Result<Answer, Error> {
+        match sqlx::query(
+            "SELECT id, content, corresponding_question FROM answers
+            WHERE id = $1 AND (status = 'published' OR $2 OR account_id = $3)",
+        )
+        .bind(id)
+        .bind(viewer.moderator)
+        .bind(viewer.account_id.as_ref().map(|id| id.0))
+        .map(|row: PgRow| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+        })
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ContentStatus,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers (content, corresponding_question, account_id, status)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, content, corresponding_question",
+        )
+        .bind(answer.content)
+        .bind(answer.question_id.0)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(|row| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+        })
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_answer(&self, answer: Answer, status: ContentStatus) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers
+            SET content = $1, status = $2
+            WHERE id = $3
+            RETURNING id, content, corresponding_question",
+        )
+        .bind(answer.content)
+        .bind(status.as_str())
+        .bind(answer.id.0)
+        .map(|row| Answer {
+            id: AnswerId(row.get("id")),
+            content: row.get("content"),
+            question_id: QuestionId(row.get("corresponding_question")),
+        })
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_answer(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM answers WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, Error> {
+        match sqlx::query("SELECT * from answers where id = $1 and account_id = $2")
+            .bind(answer_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AccountRepository for Store {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query("INSERT INTO accounts (email, password, role) VALUES ($1, $2, $3)")
+            .bind(account.email)
+            .bind(account.password)
+            .bind(account.role.as_str())
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => Ok(true),
+            // unique_violation on the e-mail address
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+                Err(Error::AccountAlreadyExists)
+            }
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * from accounts where email = $1")
+            .bind(email)
+            .map(|row: PgRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: row.get::<String, _>("role").parse().unwrap_or_default(),
+            })
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> Result<Account, Error> {
+        match sqlx::query("SELECT * from accounts where id = $1")
+            .bind(id)
+            .map(|row: PgRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: row.get::<String, _>("role").parse().unwrap_or_default(),
+            })
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Error> {
+        match sqlx::query("SELECT * from accounts ORDER BY id")
+            .map(|row: PgRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: row.get::<String, _>("role").parse().unwrap_or_default(),
+            })
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn set_account_role(&self, id: i32, role: Role) -> Result<Account, Error> {
+        match sqlx::query(
+            "UPDATE accounts
+            SET role = $1
+            WHERE id = $2
+            RETURNING id, email, password, role",
+        )
+        .bind(role.as_str())
+        .bind(id)
+        .map(|row: PgRow| Account {
+            id: Some(AccountId(row.get("id"))),
+            email: row.get("email"),
+            password: row.get("password"),
+            role: row.get::<String, _>("role").parse().unwrap_or_default(),
+        })
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn set_account_password(&self, id: i32, password: String) -> Result<bool, Error> {
+        match sqlx::query("UPDATE accounts SET password = $1 WHERE id = $2")
+            .bind(password)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_account(&self, id: i32) -> Result<bool, Error> {
+        match sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TokenRepository for Store {
+    async fn revoke_session(&self, sid: &str, expires: DateTime<Utc>) -> Result<bool, Error> {
+        // Entries are only needed until the tokens would have expired anyway
+        if let Err(e) = sqlx::query("DELETE FROM revoked_sessions WHERE expires_on < NOW()")
+            .execute(&self.pool)
+            .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO revoked_sessions (sid, expires_on)
+            VALUES ($1, $2)
+            ON CONFLICT (sid) DO NOTHING",
+        )
+        .bind(sid)
+        .bind(expires)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_session_revoked(&self, sid: &str) -> Result<bool, Error> {
+        match sqlx::query("SELECT sid from revoked_sessions where sid = $1")
+            .bind(sid)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(revoked) => Ok(revoked.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for Store {
+    async fn add_flag(
+        &self,
+        target: ContentRef,
+        account_id: AccountId,
+        reason: String,
+    ) -> Result<Flag, Error> {
+        self.get_content_status(target).await?;
+
+        match sqlx::query(
+            "INSERT INTO flags (content_type, content_id, account_id, reason)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            RETURNING *",
+        )
+        .bind(target.kind.as_str())
+        .bind(target.id)
+        .bind(account_id.0)
+        .bind(reason)
+        .map(|row: PgRow| flag_from_row(&row))
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(flag)) => Ok(flag),
+            Ok(None) => Err(Error::AlreadyFlagged),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_moderation_queue(&self) -> Result<Vec<QueueItem>, Error> {
+        let flags = match sqlx::query(
+            "SELECT * FROM flags
+            WHERE decision_id IS NULL
+            ORDER BY created_on, id",
+        )
+        .map(|row: PgRow| flag_from_row(&row))
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(flags) => flags,
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+
+        match sqlx::query(
+            "SELECT 'question' AS kind, q.id, q.id AS question_id, q.title, q.content,
+                q.status, q.account_id, q.created_on
+            FROM questions q
+            WHERE q.status = 'pending' OR EXISTS (
+                SELECT 1 FROM flags f
+                WHERE f.content_type = 'question' AND f.content_id = q.id
+                    AND f.decision_id IS NULL
+            )
+            UNION ALL
+            SELECT 'answer' AS kind, a.id, a.corresponding_question AS question_id, NULL,
+                a.content, a.status, a.account_id, a.created_on
+            FROM answers a
+            WHERE a.status = 'pending' OR EXISTS (
+                SELECT 1 FROM flags f
+                WHERE f.content_type = 'answer' AND f.content_id = a.id
+                    AND f.decision_id IS NULL
+            )
+            ORDER BY created_on, id",
+        )
+        .map(|row: PgRow| {
+            let target = content_ref(&row, "kind", "id");
+            QueueItem {
+                target,
+                question_id: row.get("question_id"),
+                title: row.get("title"),
+                content: row.get("content"),
+                status: stored_status(row.get("status")),
+                account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+                flags: flags
+                    .iter()
+                    .filter(|flag| flag.target == target)
+                    .cloned()
+                    .collect(),
+            }
+        })
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(queue) => Ok(queue),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_content_status(&self, target: ContentRef) -> Result<ContentStatus, Error> {
+        match sqlx::query(&format!(
+            "SELECT status FROM {} WHERE id = $1",
+            content_table(target.kind)
+        ))
+        .bind(target.id)
+        .map(|row: PgRow| stored_status(row.get("status")))
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(Some(status)) => Ok(status),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn set_content_status(
+        &self,
+        target: ContentRef,
+        status: ContentStatus,
+    ) -> Result<bool, Error> {
+        match sqlx::query(&format!(
+            "UPDATE {} SET status = $1 WHERE id = $2",
+            content_table(target.kind)
+        ))
+        .bind(status.as_str())
+        .bind(target.id)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_decision(
+        &self,
+        target: ContentRef,
+        moderator_id: AccountId,
+        action: ModerationAction,
+        note: Option<String>,
+    ) -> Result<Decision, Error> {
+        match sqlx::query(
+            "WITH decision AS (
+                INSERT INTO moderation_decisions
+                    (content_type, content_id, moderator_id, action, note)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+            ), resolved AS (
+                UPDATE flags SET decision_id = decision.id
+                FROM decision
+                WHERE flags.content_type = $1 AND flags.content_id = $2
+                    AND flags.decision_id IS NULL
+            )
+            SELECT * FROM decision",
+        )
+        .bind(target.kind.as_str())
+        .bind(target.id)
+        .bind(moderator_id.0)
+        .bind(action.as_str())
+        .bind(note)
+        .map(|row: PgRow| Decision {
+            id: row.get("id"),
+            target: content_ref(&row, "content_type", "content_id"),
+            moderator_id: AccountId(row.get("moderator_id")),
+            action: row
+                .get::<String, _>("action")
+                .parse()
+                .expect("action is only written by the store"),
+            note: row.get("note"),
+            created_on: row.get("created_on"),
+        })
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(decision) => Ok(decision),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl HealthRepository for Store {
+    async fn ping(&self) -> Result<(), Error> {
+        match sqlx::query("SELECT 1").execute(&self.pool).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn pending_migrations(&self) -> Result<usize, Error> {
+        let mut conn = match self.pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::DatabaseQueryError(e));
+            }
+        };
+
+        let applied = match conn.list_applied_migrations().await {
+            Ok(applied) => applied,
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                return Err(Error::MigrationError(e));
+            }
+        };
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .filter(|migration| !applied.iter().any(|a| a.version == migration.version))
+            .count())
+    }
+}
+
+/// Bulk transfer of questions for the `questions export` and
+/// `questions import` admin commands. Not part of [`super::Repository`],
+/// since the in-memory store can't be reached from outside the server.
+impl Store {
+    /// Every question with its answers and tags regardless of status,
+    /// ordered by id
+    pub async fn export_questions(&self) -> Result<Vec<QuestionTransfer>, Error> {
+        let questions =
+            sqlx::query("SELECT id, title, content, tags, status FROM questions ORDER BY id")
+                .map(|row: PgRow| QuestionTransfer {
+                    id: Some(row.get("id")),
+                    title: row.get("title"),
+                    content: row.get("content"),
+                    tags: row.get("tags"),
+                    status: stored_status(row.get("status")),
+                    answers: vec![],
+                })
+                .fetch_all(&self.pool)
+                .await;
+        let answers = sqlx::query(
+            "SELECT corresponding_question, content, status FROM answers
+            WHERE corresponding_question IS NOT NULL
+            ORDER BY id",
+        )
+        .map(|row: PgRow| {
+            (
+                row.get::<i32, _>("corresponding_question"),
+                AnswerTransfer {
+                    content: row.get("content"),
+                    status: stored_status(row.get("status")),
+                },
+            )
+        })
+        .fetch_all(&self.pool)
+        .await;
+
+        match (questions, answers) {
+            (Ok(mut questions), Ok(answers)) => {
+                let mut answers_by_question: HashMap<i32, Vec<AnswerTransfer>> = HashMap::new();
+                for (question_id, answer) in answers {
+                    answers_by_question
+                        .entry(question_id)
+                        .or_default()
+                        .push(answer);
+                }
+                for question in &mut questions {
+                    if let Some(answers) =
+                        question.id.and_then(|id| answers_by_question.remove(&id))
+                    {
+                        question.answers = answers;
+                    }
+                }
+                Ok(questions)
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    /// Adds the questions and their answers in a single transaction, so a
+    /// failing import leaves nothing behind. Imported ids are kept unless
+    /// they are taken, then `conflict` decides. A dry run goes through the
+    /// same statements and rolls back at the end. `progress` is called
+    /// after each question.
+    pub async fn import_questions(
+        &self,
+        questions: Vec<QuestionTransfer>,
+        owner: Option<AccountId>,
+        conflict: ConflictMode,
+        dry_run: bool,
+        progress: impl Fn(&ImportReport),
+    ) -> Result<ImportReport, Error> {
+        match self
+            .import_transaction(questions, owner, conflict, dry_run, progress)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn import_transaction(
+        &self,
+        questions: Vec<QuestionTransfer>,
+        owner: Option<AccountId>,
+        conflict: ConflictMode,
+        dry_run: bool,
+        progress: impl Fn(&ImportReport),
+    ) -> Result<ImportReport, sqlx::Error> {
+        let owner = owner.map(|owner| owner.0);
+        let mut report = ImportReport::default();
+        let mut tx = self.pool.begin().await?;
+
+        // New ids must not collide with imported ones further down the
+        // file. The sequence isn't transactional, a dry run only leaves a
+        // gap in the ids behind.
+        let highest_id = questions.iter().filter_map(|q| q.id).max().unwrap_or(0);
+        sqlx::query(
+            "SELECT setval('questions_id_seq', $1) FROM questions_id_seq
+            WHERE last_value < $1",
+        )
+        .bind(i64::from(highest_id))
+        .execute(&mut tx)
+        .await?;
+
+        for question in questions {
+            let taken = match question.id {
+                Some(id) => sqlx::query("SELECT id FROM questions WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&mut tx)
+                    .await?
+                    .is_some(),
+                None => false,
+            };
+
+            let question_id: i32 = match (question.id, taken, conflict) {
+                (Some(_), true, ConflictMode::Skip) => {
+                    report.skipped += 1;
+                    progress(&report);
+                    continue;
+                }
+                (Some(id), true, ConflictMode::Overwrite) => {
+                    sqlx::query(
+                        "INSERT INTO question_revisions (question_id, account_id, title, content, tags)
+                        SELECT id, $2, title, content, tags FROM questions
+                        WHERE id = $1 AND (title, content, tags) IS DISTINCT FROM ($3, $4, $5)",
+                    )
+                    .bind(id)
+                    .bind(owner)
+                    .bind(&question.title)
+                    .bind(&question.content)
+                    .bind(&question.tags)
+                    .execute(&mut tx)
+                    .await?;
+                    sqlx::query(
+                        "UPDATE questions SET title = $1, content = $2, tags = $3, status = $4
+                        WHERE id = $5",
+                    )
+                    .bind(&question.title)
+                    .bind(&question.content)
+                    .bind(&question.tags)
+                    .bind(question.status.as_str())
+                    .bind(id)
+                    .execute(&mut tx)
+                    .await?;
+                    sqlx::query("DELETE FROM answers WHERE corresponding_question = $1")
+                        .bind(id)
+                        .execute(&mut tx)
+                        .await?;
+                    report.overwritten += 1;
+                    id
+                }
+                (Some(id), false, _) => {
+                    sqlx::query(
+                        "INSERT INTO questions (id, title, content, tags, account_id, status)
+                        VALUES ($1, $2, $3, $4, $5, $6)",
+                    )
+                    .bind(id)
+                    .bind(&question.title)
+                    .bind(&question.content)
+                    .bind(&question.tags)
+                    .bind(owner)
+                    .bind(question.status.as_str())
+                    .execute(&mut tx)
+                    .await?;
+                    report.created += 1;
+                    id
+                }
+                (id, _, _) => {
+                    let new_id = sqlx::query(
+                        "INSERT INTO questions (title, content, tags, account_id, status)
+                        VALUES ($1, $2, $3, $4, $5)
+                        RETURNING id",
+                    )
+                    .bind(&question.title)
+                    .bind(&question.content)
+                    .bind(&question.tags)
+                    .bind(owner)
+                    .bind(question.status.as_str())
+                    .fetch_one(&mut tx)
+                    .await?
+                    .get("id");
+                    match id {
+                        Some(_) => report.renumbered += 1,
+                        None => report.created += 1,
+                    }
+                    new_id
+                }
+            };
+
+            for answer in &question.answers {
+                sqlx::query(
+                    "INSERT INTO answers (content, corresponding_question, account_id, status)
+                    VALUES ($1, $2, $3, $4)",
+                )
+                .bind(&answer.content)
+                .bind(question_id)
+                .bind(owner)
+                .bind(answer.status.as_str())
+                .execute(&mut tx)
+                .await?;
+                report.answers += 1;
+            }
+
+            progress(&report);
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_statuses_fall_back_to_the_default() {
+        assert_eq!(stored_status("hidden".to_string()), ContentStatus::Hidden);
+        assert_eq!(stored_status("pending".to_string()), ContentStatus::Pending);
+        assert_eq!(
+            stored_status("archived".to_string()),
+            ContentStatus::Published
+        );
+    }
+}
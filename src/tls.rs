@@ -0,0 +1,87 @@
+use std::fs;
+
+use serde::Deserialize;
+use warp::{
+    filters::path::FullPath,
+    http::{StatusCode, Uri},
+    Filter, Reply,
+};
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Serve HTTPS instead of plain HTTP
+    pub enabled: bool,
+    /// PEM encoded certificate chain
+    pub cert_path: String,
+    /// PEM encoded private key
+    pub key_path: String,
+    /// Port of an additional plain HTTP listener which redirects every
+    /// request to HTTPS
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsConfig {
+    /// Fails early with a readable message, instead of warp panicking on
+    /// the first connection
+    pub fn check(&self) -> Result<(), String> {
+        for (name, path) in [("cert_path", &self.cert_path), ("key_path", &self.key_path)] {
+            if path.is_empty() {
+                return Err(format!("TLS is enabled, but tls.{} is not set", name));
+            }
+            if let Err(e) = fs::metadata(path) {
+                return Err(format!("Cannot read tls.{} {}: {}", name, path, e));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Redirects every request to the same path on the HTTPS listener at
+/// `https_port`, keeping the host the client asked for.
+pub fn redirect(
+    https_port: u16,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("host")
+        .and(warp::path::full())
+        .and(
+            warp::query::raw()
+                .map(Some)
+                .or(warp::any().map(|| None))
+                .unify(),
+        )
+        .map(
+            move |host: Option<String>, path: FullPath, query: Option<String>| {
+                let host = host.as_deref().and_then(|host| {
+                    // Drop the port of the HTTP listener, keeping IPv6 addresses intact
+                    match host.rsplit_once(':') {
+                        Some((name, port)) if !port.contains(']') => Some(name.to_string()),
+                        _ if host.is_empty() => None,
+                        _ => Some(host.to_string()),
+                    }
+                });
+                let authority = match (host, https_port) {
+                    (Some(host), 443) => host,
+                    (Some(host), port) => format!("{}:{}", host, port),
+                    (None, _) => {
+                        return warp::reply::with_status(
+                            "Missing Host header",
+                            StatusCode::BAD_REQUEST,
+                        )
+                        .into_response()
+                    }
+                };
+                let query = query.map(|query| format!("?{}", query)).unwrap_or_default();
+
+                match format!("https://{}{}{}", authority, path.as_str(), query).parse::<Uri>() {
+                    // 308 keeps the method and body of the request
+                    Ok(uri) => warp::redirect::permanent(uri).into_response(),
+                    Err(_) => {
+                        warp::reply::with_status("Invalid Host header", StatusCode::BAD_REQUEST)
+                            .into_response()
+                    }
+                }
+            },
+        )
+}
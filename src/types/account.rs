@@ -1,19 +1,137 @@
+use std::{fmt, str::FromStr};
+
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::moderation::ContentStatus;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
     pub exp: DateTime<Utc>,
     pub account_id: AccountId,
     pub nbf: DateTime<Utc>,
+    #[serde(default)]
+    pub role: Role,
+    /// Identifies the login the token belongs to. Access and refresh tokens
+    /// issued together share it, so revoking it invalidates both.
+    pub sid: String,
+    #[serde(default)]
+    pub kind: TokenKind,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    #[default]
+    Access,
+    Refresh,
+}
+
+/// Returned by `POST /login` and `POST /token/refresh`
+#[derive(Serialize, Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires: DateTime<Utc>,
+}
+
+/// Request body for `POST /token/refresh`
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+impl Session {
+    /// Moderators and admins may edit or delete content they don't own.
+    pub fn can_moderate(&self) -> bool {
+        self.role >= Role::Moderator
+    }
+}
+
+/// Who is reading content, used to decide whether pending posts are shown.
+/// Anonymous requests see published content only.
+#[derive(Debug, Clone, Default)]
+pub struct Viewer {
+    pub account_id: Option<AccountId>,
+    pub moderator: bool,
+}
+
+impl Viewer {
+    /// Pending content is visible to its author and to moderators.
+    pub fn can_see(&self, status: ContentStatus, author: Option<&AccountId>) -> bool {
+        status == ContentStatus::Published
+            || self.moderator
+            || (self.account_id.is_some() && self.account_id.as_ref() == author)
+    }
+}
+
+impl From<Option<Session>> for Viewer {
+    fn from(session: Option<Session>) -> Self {
+        match session {
+            Some(session) => Viewer {
+                moderator: session.can_moderate(),
+                account_id: Some(session.account_id),
+            },
+            None => Viewer::default(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     pub id: Option<AccountId>,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password: String,
+    #[serde(default)]
+    pub role: Role,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AccountId(pub i32);
+
+/// Permission level of an account. Roles are ordered, so every role
+/// includes the permissions of the ones declared before it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("unknown role: {}", s)),
+        }
+    }
+}
+
+/// Request body for `PUT /accounts/{id}/role`
+#[derive(Deserialize, Debug, Clone)]
+pub struct RoleUpdate {
+    pub role: Role,
+}
@@ -2,7 +2,7 @@ use super::question::QuestionId;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct AnswerId(pub String);
+pub struct AnswerId(pub i32);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NewAnswer {
@@ -10,6 +10,12 @@ pub struct NewAnswer {
     pub question_id: QuestionId,
 }
 
+/// Request body for `PUT /answers/{id}`, an answer stays with its question
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnswerUpdate {
+    pub content: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Answer {
     pub id: AnswerId,
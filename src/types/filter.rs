@@ -0,0 +1,256 @@
+use chrono::prelude::*;
+use handle_errors::Error;
+use std::collections::HashMap;
+
+/// Whether a question needs to carry any or all of the requested tags
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Newest,
+    #[default]
+    Oldest,
+    MostAnswered,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+            SortOrder::MostAnswered => "most_answered",
+        }
+    }
+}
+
+/// Filter and sort options for the question list which are getting
+/// extracted from query params
+#[derive(Debug, Default, Clone)]
+pub struct QuestionFilter {
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    pub author: Option<i32>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub answered: Option<bool>,
+    pub sort: SortOrder,
+}
+
+const KNOWN_PARAMETERS: [&str; 10] = [
+    "tag",
+    "tag_match",
+    "author",
+    "created_before",
+    "created_after",
+    "answered",
+    "sort",
+    "limit",
+    "offset",
+    "cursor",
+];
+
+/// Extract filter and sort parameters from the `/questions` route
+/// # Example query
+/// Unanswered questions tagged rust or async from the last week, newest first
+/// `/questions?tag=rust,async&answered=false&created_after=2022-07-01&sort=newest`
+/// # Example usage
+/// ```rust
+/// let mut query = HashMap::new();
+/// query.insert("tag".to_string(), "rust,async".to_string());
+/// query.insert("tag_match".to_string(), "all".to_string());
+/// let f = types::filter::extract_filter(&query).unwrap();
+/// assert_eq!(f.tags, vec!["rust", "async"]);
+/// assert_eq!(f.tag_match, TagMatch::All);
+/// ```
+pub fn extract_filter(params: &HashMap<String, String>) -> Result<QuestionFilter, Error> {
+    if let Some(name) = params
+        .keys()
+        .find(|name| !KNOWN_PARAMETERS.contains(&name.as_str()))
+    {
+        return Err(Error::UnknownParameter(name.clone()));
+    }
+
+    let mut filter = QuestionFilter::default();
+
+    if let Some(tags) = params.get("tag") {
+        filter.tags = tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+    }
+
+    if let Some(tag_match) = params.get("tag_match") {
+        filter.tag_match = match tag_match.as_str() {
+            "any" => TagMatch::Any,
+            "all" => TagMatch::All,
+            _ => return Err(invalid("tag_match", "expected `any` or `all`")),
+        };
+    }
+
+    if let Some(author) = params.get("author") {
+        filter.author = Some(
+            author
+                .parse::<i32>()
+                .map_err(|_| invalid("author", "expected an account id"))?,
+        );
+    }
+
+    if let Some(date) = params.get("created_before") {
+        filter.created_before = Some(parse_date("created_before", date)?);
+    }
+
+    if let Some(date) = params.get("created_after") {
+        filter.created_after = Some(parse_date("created_after", date)?);
+    }
+
+    if let Some(answered) = params.get("answered") {
+        filter.answered = Some(
+            answered
+                .parse::<bool>()
+                .map_err(|_| invalid("answered", "expected `true` or `false`"))?,
+        );
+    }
+
+    if let Some(sort) = params.get("sort") {
+        filter.sort = match sort.as_str() {
+            "newest" => SortOrder::Newest,
+            "oldest" => SortOrder::Oldest,
+            "most_answered" => SortOrder::MostAnswered,
+            _ => {
+                return Err(invalid(
+                    "sort",
+                    "expected `newest`, `oldest` or `most_answered`",
+                ))
+            }
+        };
+    }
+
+    if let (Some(before), Some(after)) = (filter.created_before, filter.created_after) {
+        if after >= before {
+            return Err(invalid(
+                "created_after",
+                "has to be earlier than `created_before`",
+            ));
+        }
+    }
+
+    Ok(filter)
+}
+
+/// Accepts RFC 3339 timestamps as well as plain dates, which are taken as
+/// midnight UTC.
+fn parse_date(name: &str, value: &str) -> Result<DateTime<Utc>, Error> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| DateTime::<Utc>::from_utc(date.and_hms(0, 0, 0), Utc))
+        .map_err(|_| {
+            invalid(
+                name,
+                "expected a date like `2022-07-01` or an RFC 3339 timestamp",
+            )
+        })
+}
+
+fn invalid(name: &str, reason: &str) -> Error {
+    Error::InvalidParameter(name.to_string(), reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn invalid_parameter(pairs: &[(&str, &str)]) -> String {
+        match extract_filter(&query(pairs)) {
+            Err(Error::InvalidParameter(name, _)) => name,
+            other => panic!("expected an invalid parameter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_parameters_give_the_default_filter() {
+        let filter = extract_filter(&query(&[])).unwrap();
+
+        assert!(filter.tags.is_empty());
+        assert_eq!(filter.tag_match, TagMatch::Any);
+        assert_eq!(filter.sort, SortOrder::Oldest);
+        assert_eq!(filter.author, None);
+        assert_eq!(filter.answered, None);
+    }
+
+    #[test]
+    fn all_parameters_are_extracted() {
+        let filter = extract_filter(&query(&[
+            ("tag", "rust, async,,"),
+            ("tag_match", "all"),
+            ("author", "7"),
+            ("created_after", "2022-07-01"),
+            ("created_before", "2022-07-08T12:00:00+02:00"),
+            ("answered", "false"),
+            ("sort", "most_answered"),
+            ("limit", "10"),
+        ]))
+        .unwrap();
+
+        assert_eq!(filter.tags, ["rust", "async"]);
+        assert_eq!(filter.tag_match, TagMatch::All);
+        assert_eq!(filter.author, Some(7));
+        assert_eq!(
+            filter.created_after,
+            Some(Utc.ymd(2022, 7, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            filter.created_before,
+            Some(Utc.ymd(2022, 7, 8).and_hms(10, 0, 0))
+        );
+        assert_eq!(filter.answered, Some(false));
+        assert_eq!(filter.sort, SortOrder::MostAnswered);
+    }
+
+    #[test]
+    fn unknown_parameters_are_refused() {
+        assert!(matches!(
+            extract_filter(&query(&[("tags", "rust")])),
+            Err(Error::UnknownParameter(name)) if name == "tags"
+        ));
+    }
+
+    #[test]
+    fn malformed_values_are_refused() {
+        assert_eq!(invalid_parameter(&[("tag_match", "some")]), "tag_match");
+        assert_eq!(invalid_parameter(&[("author", "alice")]), "author");
+        assert_eq!(
+            invalid_parameter(&[("created_before", "July")]),
+            "created_before"
+        );
+        assert_eq!(invalid_parameter(&[("answered", "yes")]), "answered");
+        assert_eq!(invalid_parameter(&[("sort", "popular")]), "sort");
+    }
+
+    #[test]
+    fn date_range_has_to_be_in_order() {
+        assert_eq!(
+            invalid_parameter(&[
+                ("created_after", "2022-07-08"),
+                ("created_before", "2022-07-01"),
+            ]),
+            "created_after"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Response of `GET /health`
+#[derive(Serialize, Debug, Clone)]
+pub struct Health {
+    pub status: &'static str,
+}
+
+/// Response of `GET /version`
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Crate version, git commit and target platform, as logged on startup
+    pub build_id: &'static str,
+    pub commit: &'static str,
+    pub platform: &'static str,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+/// Outcome of checking a single dependency
+#[derive(Serialize, Debug, Clone)]
+pub struct DependencyCheck {
+    pub status: CheckStatus,
+    /// Whether the instance can't serve requests while this check is down
+    pub critical: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    Ready,
+    /// Ready, but a non-critical dependency is down
+    Degraded,
+    NotReady,
+}
+
+/// Response of `GET /ready`
+#[derive(Serialize, Debug, Clone)]
+pub struct Readiness {
+    pub status: ReadinessStatus,
+    pub checks: BTreeMap<&'static str, DependencyCheck>,
+}
+
+impl Readiness {
+    pub fn new(checks: BTreeMap<&'static str, DependencyCheck>) -> Self {
+        let down = |critical: bool| {
+            checks
+                .values()
+                .any(|check| check.critical == critical && check.status == CheckStatus::Down)
+        };
+
+        let status = if down(true) {
+            ReadinessStatus::NotReady
+        } else if down(false) {
+            ReadinessStatus::Degraded
+        } else {
+            ReadinessStatus::Ready
+        };
+
+        Readiness { status, checks }
+    }
+}
@@ -1,4 +1,10 @@
 pub mod account;
 pub mod answer;
+pub mod filter;
+pub mod health;
+pub mod moderation;
 pub mod pagination;
 pub mod question;
+pub mod revision;
+pub mod search;
+pub mod transfer;
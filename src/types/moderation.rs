@@ -0,0 +1,183 @@
+use std::{fmt, str::FromStr};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::account::AccountId;
+
+/// Whether a question or answer is shown to everyone. Pending content is
+/// held for review, hidden content was taken down by a moderator. Both are
+/// only visible to their author and moderators.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentStatus {
+    #[default]
+    Published,
+    Pending,
+    Hidden,
+}
+
+impl ContentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentStatus::Published => "published",
+            ContentStatus::Pending => "pending",
+            ContentStatus::Hidden => "hidden",
+        }
+    }
+}
+
+impl fmt::Display for ContentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ContentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "published" => Ok(ContentStatus::Published),
+            "pending" => Ok(ContentStatus::Pending),
+            "hidden" => Ok(ContentStatus::Hidden),
+            _ => Err(format!("unknown content status: {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    Question,
+    Answer,
+}
+
+impl ContentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentKind::Question => "question",
+            ContentKind::Answer => "answer",
+        }
+    }
+}
+
+impl FromStr for ContentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "question" => Ok(ContentKind::Question),
+            "answer" => Ok(ContentKind::Answer),
+            _ => Err(format!("unknown content kind: {}", s)),
+        }
+    }
+}
+
+/// Points to a single question or answer
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentRef {
+    pub kind: ContentKind,
+    pub id: i32,
+}
+
+/// Request body for `POST /questions/{id}/flags` and `POST /answers/{id}/flags`
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewFlag {
+    pub reason: String,
+}
+
+/// A report of a question or answer by a user. Flags stay open until a
+/// moderator made a decision on the content.
+#[derive(Serialize, Debug, Clone)]
+pub struct Flag {
+    pub id: i32,
+    pub target: ContentRef,
+    pub account_id: Option<AccountId>,
+    pub reason: String,
+    pub created_on: DateTime<Utc>,
+    #[serde(skip_serializing)]
+    pub decision_id: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    /// Publish the content as it is
+    Approve,
+    /// Replace the content and publish it
+    Edit,
+    /// Take the content down, keeping it for its author
+    Hide,
+    Delete,
+}
+
+impl ModerationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationAction::Approve => "approve",
+            ModerationAction::Edit => "edit",
+            ModerationAction::Hide => "hide",
+            ModerationAction::Delete => "delete",
+        }
+    }
+}
+
+impl fmt::Display for ModerationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ModerationAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "approve" => Ok(ModerationAction::Approve),
+            "edit" => Ok(ModerationAction::Edit),
+            "hide" => Ok(ModerationAction::Hide),
+            "delete" => Ok(ModerationAction::Delete),
+            _ => Err(format!("unknown moderation action: {}", s)),
+        }
+    }
+}
+
+/// Request body for `POST /moderation/questions/{id}/decisions` and
+/// `POST /moderation/answers/{id}/decisions`. The `edit` action takes the
+/// new `title`, `content` and `tags`, fields left out are kept.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DecisionRequest {
+    pub action: ModerationAction,
+    pub note: Option<String>,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// A moderator's decision on a question or answer, which resolves all of
+/// its open flags.
+#[derive(Serialize, Debug, Clone)]
+pub struct Decision {
+    pub id: i32,
+    pub target: ContentRef,
+    pub moderator_id: AccountId,
+    pub action: ModerationAction,
+    pub note: Option<String>,
+    pub created_on: DateTime<Utc>,
+}
+
+/// Content waiting for a moderator, either because it was flagged or
+/// because it was held by the moderation policy.
+#[derive(Serialize, Debug, Clone)]
+pub struct QueueItem {
+    #[serde(flatten)]
+    pub target: ContentRef,
+    /// The question itself, or the one an answer belongs to
+    pub question_id: i32,
+    pub title: Option<String>,
+    pub content: String,
+    pub status: ContentStatus,
+    pub account_id: Option<AccountId>,
+    pub flags: Vec<Flag>,
+}
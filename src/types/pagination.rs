@@ -1,4 +1,7 @@
+use super::filter::SortOrder;
+use chrono::NaiveDateTime;
 use handle_errors::Error;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Pagination struct which is getting extract
@@ -12,35 +15,301 @@ pub struct Pagination {
 /// Extract query parameters from the `/questions` route
 /// # Example query
 /// GET requests to this route can have a pagination attached so we just
-/// return the questions we need
-/// `/questions?start=1&end=10`
+/// return the questions we need. Both parameters are optional, `limit` is
+/// unbounded and `offset` 0 if left out.
+/// `/questions?limit=10&offset=20`
 /// # Example usage
 /// ```rust
 /// let mut query = HashMap::new();
-/// query.insert("limit".to_string(), "1".to_string());
 /// query.insert("offset".to_string(), "10".to_string());
 /// let p = types::pagination::extract_pagination(query).unwrap();
-/// assert_eq!(p.limit, Some(1));
+/// assert_eq!(p.limit, None);
 /// assert_eq!(p.offset, 10);
 /// ```
-
 pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
-    if params.contains_key("limit") & params.contains_key("offset") {
-        return Ok(Pagination {
-            limit: Some(
-                params
-                    .get("limit")
-                    .unwrap()
-                    .parse::<u32>()
-                    .map_err(Error::ParseError)?,
-            ),
-            offset: params
-                .get("end")
+    let limit = match params.get("limit") {
+        Some(limit) => Some(limit.parse::<u32>().map_err(Error::ParseError)?),
+        None => None,
+    };
+    let offset = match params.get("offset") {
+        Some(offset) => offset.parse::<u32>().map_err(Error::ParseError)?,
+        None => 0,
+    };
+
+    Ok(Pagination { limit, offset })
+}
+
+/// Maximum and default page size for cursor based pagination
+pub const MAX_PAGE_SIZE: u32 = 100;
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// Position of a row in a sorted list. `answer_count` only takes part in
+/// the ordering when sorting by the number of answers, otherwise it is 0.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CursorKey {
+    pub answer_count: i64,
+    pub created_on: NaiveDateTime,
+    pub id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    After,
+    Before,
+}
+
+/// Opaque position handed out to clients to request the page before or
+/// after a given row. Cursors are only valid for the sort order they were
+/// issued for.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cursor {
+    #[serde(flatten)]
+    pub key: CursorKey,
+    pub direction: Direction,
+    pub sort: String,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str, sort: SortOrder) -> Result<Self, Error> {
+        let cursor = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Cursor>(&json).ok())
+            .ok_or_else(|| {
+                Error::InvalidParameter("cursor".to_string(), "malformed cursor".to_string())
+            })?;
+
+        if cursor.sort != sort.as_str() {
+            return Err(Error::InvalidParameter(
+                "cursor".to_string(),
+                "was issued for a different sort order".to_string(),
+            ));
+        }
+
+        Ok(cursor)
+    }
+
+    /// Whether rows have to be walked in ascending key order to reach the
+    /// requested page.
+    pub fn ascending(sort: SortOrder, direction: Direction) -> bool {
+        (sort == SortOrder::Oldest) ^ (direction == Direction::Before)
+    }
+}
+
+/// One page of a cursor paginated list
+#[derive(Serialize, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from up to `limit + 1` rows in the order they were
+    /// walked; the extra row only signals that there are more.
+    pub fn from_rows(
+        mut rows: Vec<(T, CursorKey)>,
+        cursor: Option<&Cursor>,
+        sort: SortOrder,
+        limit: u32,
+        total: i64,
+    ) -> Self {
+        let has_more = rows.len() > limit as usize;
+        rows.truncate(limit as usize);
+
+        let backwards = matches!(cursor, Some(c) if c.direction == Direction::Before);
+        if backwards {
+            rows.reverse();
+        }
+
+        let (has_prev, has_next) = if backwards {
+            (has_more, true)
+        } else {
+            (cursor.is_some(), has_more)
+        };
+
+        let link = |key: Option<&CursorKey>, direction| {
+            key.map(|key| {
+                Cursor {
+                    key: key.clone(),
+                    direction,
+                    sort: sort.as_str().to_string(),
+                }
+                .encode()
+            })
+        };
+
+        Page {
+            next: if has_next {
+                link(rows.last().map(|(_, key)| key), Direction::After)
+            } else {
+                None
+            },
+            prev: if has_prev {
+                link(rows.first().map(|(_, key)| key), Direction::Before)
+            } else {
+                None
+            },
+            items: rows.into_iter().map(|(item, _)| item).collect(),
+            total,
+        }
+    }
+}
+
+/// Extract cursor pagination parameters from the `/questions` route
+/// # Example query
+/// `/questions?limit=10&cursor=eyJhbnN3ZXJfY291bnQiOjAsImNyZWF0...`
+/// Without a cursor the first page is returned.
+pub fn extract_cursor_pagination(
+    params: &HashMap<String, String>,
+    sort: SortOrder,
+) -> Result<(Option<Cursor>, u32), Error> {
+    let limit = match params.get("limit") {
+        Some(limit) => limit.parse::<u32>().map_err(Error::ParseError)?,
+        None => DEFAULT_PAGE_SIZE,
+    };
+
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(Error::InvalidParameter(
+            "limit".to_string(),
+            format!("has to be between 1 and {}", MAX_PAGE_SIZE),
+        ));
+    }
+
+    let cursor = match params.get("cursor") {
+        Some(cursor) => Some(Cursor::decode(cursor, sort)?),
+        None => None,
+    };
+
+    Ok((cursor, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: i32) -> CursorKey {
+        CursorKey {
+            answer_count: 0,
+            created_on: NaiveDateTime::from_timestamp(1_656_633_600 + i64::from(id), 0),
+            id,
+        }
+    }
+
+    /// Rows with the given ids, in the order they were walked
+    fn rows(ids: &[i32]) -> Vec<(i32, CursorKey)> {
+        ids.iter().map(|id| (*id, key(*id))).collect()
+    }
+
+    fn cursor(id: i32, direction: Direction) -> Cursor {
+        Cursor {
+            key: key(id),
+            direction,
+            sort: SortOrder::Oldest.as_str().to_string(),
+        }
+    }
+
+    fn decode(cursor: &Option<String>) -> Cursor {
+        Cursor::decode(cursor.as_deref().unwrap(), SortOrder::Oldest).unwrap()
+    }
+
+    #[test]
+    fn first_page_only_links_to_the_next_one() {
+        let page = Page::from_rows(rows(&[1, 2, 3]), None, SortOrder::Oldest, 2, 3);
+
+        assert_eq!(page.items, [1, 2]);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.prev, None);
+        assert_eq!(decode(&page.next), cursor(2, Direction::After));
+    }
+
+    #[test]
+    fn single_page_has_no_links() {
+        let page = Page::from_rows(rows(&[1, 2]), None, SortOrder::Oldest, 2, 2);
+
+        assert_eq!(page.items, [1, 2]);
+        assert_eq!(page.next, None);
+        assert_eq!(page.prev, None);
+    }
+
+    #[test]
+    fn pages_after_a_cursor_link_back() {
+        let after = cursor(2, Direction::After);
+        let page = Page::from_rows(rows(&[3, 4]), Some(&after), SortOrder::Oldest, 2, 4);
+
+        assert_eq!(page.items, [3, 4]);
+        assert_eq!(decode(&page.prev), cursor(3, Direction::Before));
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn pages_before_a_cursor_are_put_back_in_order() {
+        let before = cursor(5, Direction::Before);
+        let page = Page::from_rows(rows(&[4, 3, 2]), Some(&before), SortOrder::Oldest, 2, 5);
+
+        assert_eq!(page.items, [3, 4]);
+        assert_eq!(decode(&page.prev), cursor(3, Direction::Before));
+        assert_eq!(decode(&page.next), cursor(4, Direction::After));
+    }
+
+    #[test]
+    fn walking_back_to_the_start_has_no_prev_link() {
+        let before = cursor(3, Direction::Before);
+        let page = Page::from_rows(rows(&[2, 1]), Some(&before), SortOrder::Oldest, 2, 5);
+
+        assert_eq!(page.items, [1, 2]);
+        assert_eq!(page.prev, None);
+        assert_eq!(decode(&page.next), cursor(2, Direction::After));
+    }
+
+    #[test]
+    fn cursors_only_work_for_their_sort_order() {
+        let encoded = cursor(1, Direction::After).encode();
+
+        assert!(Cursor::decode(&encoded, SortOrder::Oldest).is_ok());
+        assert!(matches!(
+            Cursor::decode(&encoded, SortOrder::Newest),
+            Err(Error::InvalidParameter(name, _)) if name == "cursor"
+        ));
+        assert!(Cursor::decode("not a cursor", SortOrder::Oldest).is_err());
+    }
+
+    #[test]
+    fn page_size_is_bounded() {
+        let params = |limit: &str| HashMap::from([("limit".to_string(), limit.to_string())]);
+
+        assert_eq!(
+            extract_cursor_pagination(&HashMap::new(), SortOrder::Oldest)
+                .unwrap()
+                .1,
+            DEFAULT_PAGE_SIZE
+        );
+        assert_eq!(
+            extract_cursor_pagination(&params("100"), SortOrder::Oldest)
                 .unwrap()
-                .parse::<u32>()
-                .map_err(Error::ParseError)?,
-        });
+                .1,
+            100
+        );
+        assert!(extract_cursor_pagination(&params("0"), SortOrder::Oldest).is_err());
+        assert!(extract_cursor_pagination(&params("101"), SortOrder::Oldest).is_err());
     }
 
-    Err(Error::MissingParameters)
+    #[test]
+    fn limit_and_offset_are_optional() {
+        let pagination =
+            extract_pagination(HashMap::from([("limit".to_string(), "10".to_string())])).unwrap();
+        assert_eq!(pagination.limit, Some(10));
+        assert_eq!(pagination.offset, 0);
+
+        assert!(matches!(
+            extract_pagination(HashMap::from([("offset".to_string(), "-1".to_string())])),
+            Err(Error::ParseError(_))
+        ));
+    }
 }
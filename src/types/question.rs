@@ -1,3 +1,4 @@
+use super::answer::Answer;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -17,3 +18,36 @@ pub struct Question {
     pub content: String,
     pub tags: Option<Vec<String>>,
 }
+
+/// Shape of the entries in the legacy `questions.json` file, which stores
+/// the question id as a string and is keyed by it.
+#[derive(Debug, Serialize, Clone, Deserialize)]
+pub struct LegacyQuestion {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+}
+
+/// A single question as returned by `GET /questions/{id}`, optionally
+/// together with all of its answers.
+#[derive(Debug, Serialize, Clone)]
+pub struct QuestionWithAnswers {
+    #[serde(flatten)]
+    pub question: Question,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answers: Option<Vec<Answer>>,
+}
+
+/// Query parameters accepted by `GET /questions/{id}`
+/// # Example query
+/// `/questions/1?answers=false` returns the question without its answers
+#[derive(Debug, Deserialize)]
+pub struct QuestionQuery {
+    #[serde(default = "default_include_answers")]
+    pub answers: bool,
+}
+
+fn default_include_answers() -> bool {
+    true
+}
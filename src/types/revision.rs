@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use similar::TextDiff;
+
+use super::{
+    account::AccountId,
+    question::{Question, QuestionId},
+};
+
+/// Title, content and tags of a question as they were before one of its
+/// edits, together with who made the edit and when.
+#[derive(Serialize, Debug, Clone)]
+pub struct QuestionRevision {
+    /// Counts the edits of the question, starting at 1
+    pub revision: i64,
+    pub question_id: QuestionId,
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+    /// Left out for edits made by an import
+    pub edited_by: Option<AccountId>,
+    pub edited_on: DateTime<Utc>,
+}
+
+impl QuestionRevision {
+    /// The question as it was before the edit, used for rolling back to it
+    pub fn question(&self) -> Question {
+        Question {
+            id: self.question_id.clone(),
+            title: self.title.clone(),
+            content: self.content.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// Unified diffs of what an edit changed, fields it didn't touch are left
+/// out. Tags are compared one per line.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RevisionDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+}
+
+/// An entry of `GET /questions/{id}/revisions`
+#[derive(Serialize, Debug, Clone)]
+pub struct RevisionWithDiff {
+    #[serde(flatten)]
+    pub revision: QuestionRevision,
+    pub diff: RevisionDiff,
+}
+
+/// Pairs every revision with the diff to the state which replaced it, that
+/// is the next revision or, for the last one, the current question.
+pub fn with_diffs(revisions: Vec<QuestionRevision>, current: &Question) -> Vec<RevisionWithDiff> {
+    let mut next = current.clone();
+    let mut next_label = "current".to_string();
+    let mut entries: Vec<RevisionWithDiff> = revisions
+        .into_iter()
+        .rev()
+        .map(|revision| {
+            let old = revision.question();
+            let label = format!("revision {}", revision.revision);
+            let labels = (label.as_str(), next_label.as_str());
+            let diff = RevisionDiff {
+                title: diff(&old.title, &next.title, labels),
+                content: diff(&old.content, &next.content, labels),
+                tags: diff(&tag_lines(&old.tags), &tag_lines(&next.tags), labels),
+            };
+            next = old;
+            next_label = label;
+            RevisionWithDiff { revision, diff }
+        })
+        .collect();
+    entries.reverse();
+
+    entries
+}
+
+fn diff(old: &str, new: &str, (old_label, new_label): (&str, &str)) -> Option<String> {
+    if old == new {
+        return None;
+    }
+
+    Some(
+        TextDiff::from_lines(old, new)
+            .unified_diff()
+            .missing_newline_hint(false)
+            .header(old_label, new_label)
+            .to_string(),
+    )
+}
+
+fn tag_lines(tags: &Option<Vec<String>>) -> String {
+    tags.as_deref().unwrap_or_default().join("\n")
+}
@@ -0,0 +1,21 @@
+use super::question::Question;
+use serde::Serialize;
+
+/// A question matching a full-text search, together with how well it
+/// matched and the matching passages. Matched terms in `title_highlight`
+/// and `snippet` are wrapped in `<b></b>`.
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub question: Question,
+    pub rank: f32,
+    pub title_highlight: String,
+    pub snippet: String,
+}
+
+/// Response of `GET /questions/search`
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResults {
+    pub total: i64,
+    pub results: Vec<SearchResult>,
+}
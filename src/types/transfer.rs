@@ -0,0 +1,169 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::moderation::ContentStatus;
+
+/// A question with its answers, as written by `questions export` and read by
+/// `questions import`. The id is written as a string, like in the legacy
+/// `questions.json`, and read from either a string or a number.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QuestionTransfer {
+    #[serde(
+        default,
+        serialize_with = "serialize_id",
+        deserialize_with = "deserialize_id"
+    )]
+    pub id: Option<i32>,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub status: ContentStatus,
+    #[serde(default)]
+    pub answers: Vec<AnswerTransfer>,
+}
+
+/// Answers get new ids on import, so only their content is kept
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnswerTransfer {
+    pub content: String,
+    #[serde(default)]
+    pub status: ContentStatus,
+}
+
+fn serialize_id<S: Serializer>(id: &Option<i32>, serializer: S) -> Result<S::Ok, S::Error> {
+    match id {
+        Some(id) => serializer.serialize_str(&id.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(i32),
+        Text(String),
+    }
+
+    match Option::<Id>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Id::Number(id)) => Ok(Some(id)),
+        Some(Id::Text(id)) if id.trim().is_empty() => Ok(None),
+        Some(Id::Text(id)) => id
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("invalid question id {:?}", id))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFormat {
+    /// An object keyed by question id, the shape of `questions.json`
+    Json,
+    /// One question object per line
+    Ndjson,
+    /// One question per row, tags separated by commas and the answers as
+    /// JSON array
+    Csv,
+}
+
+impl TransferFormat {
+    /// Guesses the format from the file extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension()?.to_str()? {
+            "json" => Some(TransferFormat::Json),
+            "ndjson" | "jsonl" => Some(TransferFormat::Ndjson),
+            "csv" => Some(TransferFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "json",
+            TransferFormat::Ndjson => "ndjson",
+            TransferFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for TransferFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransferFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(TransferFormat::Json),
+            "ndjson" => Ok(TransferFormat::Ndjson),
+            "csv" => Ok(TransferFormat::Csv),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// What happens to an imported question whose id is already taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictMode {
+    /// Keep the existing question and drop the imported one
+    #[default]
+    Skip,
+    /// Replace the existing question and all of its answers
+    Overwrite,
+    /// Add the imported question under a new id
+    Renumber,
+}
+
+impl ConflictMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictMode::Skip => "skip",
+            ConflictMode::Overwrite => "overwrite",
+            ConflictMode::Renumber => "renumber",
+        }
+    }
+}
+
+impl fmt::Display for ConflictMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ConflictMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictMode::Skip),
+            "overwrite" => Ok(ConflictMode::Overwrite),
+            "renumber" => Ok(ConflictMode::Renumber),
+            _ => Err(format!("unknown conflict mode: {}", s)),
+        }
+    }
+}
+
+/// Counts of what an import did, or would have done in a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub created: usize,
+    pub overwritten: usize,
+    pub renumbered: usize,
+    pub skipped: usize,
+    pub answers: usize,
+}
+
+impl ImportReport {
+    /// Number of questions handled so far
+    pub fn processed(&self) -> usize {
+        self.created + self.overwritten + self.renumbered + self.skipped
+    }
+}